@@ -0,0 +1,842 @@
+//! Reader for texture atlases exported by Aseprite's `--sheet` CLI option.
+//!
+//! An export is a JSON description plus a packed PNG. [`parse_atlas_json`]
+//! turns the JSON into [`AtlasData`] without touching the engine, so it can be
+//! used from tools and tests. [`load_aseprite_atlas_bytes`] and
+//! [`load_aseprite_atlas`] also upload the PNG to comfy and return an
+//! [`ImageAtlas`] ready for [`draw_frame`].
+//!
+//! Both `--format json-array` and the default `json-hash` layout load, as do
+//! sheets from TexturePacker-style packers that follow the same schema,
+//! including frames packed rotated by 90 degrees.
+//!
+//! Frames are addressed by index in export order, and tags by their full name,
+//! e.g. `"pickups/skull"` when exporting with `--split-tags`. Resolve a name
+//! once with [`ImageAtlas::tag_handle`] and keep the [`TagHandle`]; queries
+//! through a handle are plain indexing. Slices are resolved per frame, and
+//! their user data is parsed as JSON.
+//!
+//! Exports made with `--list-layers` describe each layer in [`Layer`]. With
+//! `--split-layers` every layer gets its own frames, and the layer name is
+//! recovered from the frame filename (see [`parse_frame_filename`]) so
+//! [`frame_in_layer`] can find, say, the `glow` layer of a frame.
+//!
+//! Animation events are authored as user data, either on a tag as
+//! `{"events": [{"frame": 2, "event": "spawn_sparks"}]}` with frames counted
+//! from the start of the tag, or on a cel as `{"event": "page_turn"}`.
+//! [`tag_events`] collects both.
+//!
+//! Further exports, say from game mods, can be merged into one atlas with
+//! [`ImageAtlas::add_page`]. Each keeps its own texture, and frame indices
+//! and tag handles stay unique across pages.
+
+use std::fmt;
+use std::path::Path;
+use std::collections::HashMap;
+use comfy::*;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use log::{debug, info, trace, warn};
+
+/// Pixel rectangle with the origin in the top-left corner.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct AsepriteSize {
+    pub w: i32,
+    pub h: i32
+}
+
+fn default_duration() -> i32 {
+    return 100;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteFrame {
+    /// Taken from the map key in json-hash exports.
+    #[serde(default)]
+    filename: String,
+    frame: Rect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Rect,
+    source_size: AsepriteSize,
+    #[serde(default = "default_duration")]
+    duration: i32
+}
+
+struct FramesVisitor;
+
+impl<'de> Visitor<'de> for FramesVisitor {
+    type Value = Vec<AsepriteFrame>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.write_str("an array of frames or a map from filename to frame");
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut frames = vec![];
+        while let Some(frame) = seq.next_element()? {
+            frames.push(frame);
+        }
+
+        return Ok(frames);
+    }
+
+    // Entries are read in document order, which is the frame order, so this
+    // must not go through a HashMap.
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut frames = vec![];
+        while let Some((filename, mut frame)) = map.next_entry::<String, AsepriteFrame>()? {
+            frame.filename = filename;
+            frames.push(frame);
+        }
+
+        return Ok(frames);
+    }
+}
+
+fn deserialize_frames<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<AsepriteFrame>, D::Error> {
+    return d.deserialize_any(FramesVisitor);
+}
+
+fn default_opacity() -> u8 {
+    return 255;
+}
+
+fn default_blend_mode() -> String {
+    return "normal".to_string();
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteLayer {
+    name: String,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default = "default_opacity")]
+    opacity: u8,
+    #[serde(default = "default_blend_mode")]
+    blend_mode: String,
+    #[serde(default)]
+    data: Option<String>,
+    #[serde(default)]
+    cels: Vec<AsepriteCel>
+}
+
+#[derive(Serialize, Deserialize)]
+struct AsepriteCel {
+    frame: i32,
+    #[serde(default)]
+    data: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct AsepriteFrametag {
+    name: String,
+    to: i32,
+    from: i32,
+    direction: String,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    data: Option<String>
+}
+
+/// A named frame range from the export's `frameTags`.
+pub struct Tag {
+    pub name: String,
+    pub from: i32,
+    pub to: i32,
+    /// `forward`, `reverse` or `pingpong`.
+    pub direction: String,
+    /// The tag's colour in Aseprite's timeline; white when absent.
+    pub color: Color,
+    /// The tag's user data parsed as JSON; an empty object when absent.
+    pub data: Value
+}
+
+#[derive(Serialize, Deserialize)]
+struct AspriteSliceInstance {
+    frame: i32,
+    bounds: Rect
+}
+
+#[derive(Serialize, Deserialize)]
+struct AsepriteSlice {
+    name: String,
+    #[serde(default)]
+    color: Option<String>,
+    data: Option<String>,
+    keys: Vec<AspriteSliceInstance>,
+    from: i32,
+    to: i32
+}
+
+// Only `image` and `size` are required; other packers leave the rest out or,
+// like TexturePacker, write `scale` as a number.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    #[serde(default)]
+    app: String,
+    #[serde(default)]
+    version: String,
+    image: String,
+    #[serde(default)]
+    format: String,
+    size: AsepriteSize,
+    #[serde(default)]
+    scale: Value,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteFrametag>,
+    #[serde(default)]
+    slices: Vec<AsepriteSlice>,
+    #[serde(default)]
+    layers: Vec<AsepriteLayer>
+}
+
+#[derive(Serialize, Deserialize)]
+struct AsepriteAtlas {
+    #[serde(deserialize_with = "deserialize_frames")]
+    frames: Vec<AsepriteFrame>,
+    meta: AsepriteMeta
+}
+
+/// A slice as it applies to one frame.
+pub struct Slice {
+    pub bound: Rect,
+    /// The slice's user data parsed as JSON; an empty object when absent.
+    pub data: Value,
+    /// The slice's colour in Aseprite; white when absent.
+    pub color: Color,
+    pub name: String
+}
+
+/// A layer from the export's `layers` list.
+pub struct Layer {
+    pub name: String,
+    /// Name of the enclosing group layer, if any.
+    pub group: Option<String>,
+    /// 0.0 is fully transparent, 1.0 fully opaque.
+    pub opacity: f32,
+    /// Aseprite's blend mode name, e.g. `normal`, `addition` or `screen`.
+    pub blend_mode: String,
+    /// The layer's user data parsed as JSON; an empty object when absent.
+    pub data: Value,
+    /// Cels that carry user data. Other cels are not listed in exports.
+    pub cels: Vec<Cel>
+}
+
+pub struct Cel {
+    /// Frame index in export order.
+    pub frame: usize,
+    pub data: Value
+}
+
+/// A named marker on one frame of a tag, see [`tag_events`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct FrameEvent {
+    /// Frame index in export order.
+    pub frame: usize,
+    pub name: String,
+    /// The whole marker, so events can carry extra fields such as a sound name.
+    pub data: Value
+}
+
+/// The parts of a frame filename written by Aseprite's default formats:
+/// `{title} ({layer}) {frame}.{extension}` with `--split-layers`, and
+/// `{title} {frame}.{extension}` or `{title}.{extension}` without.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FrameName {
+    pub title: String,
+    pub layer: Option<String>,
+    pub frame: Option<usize>
+}
+
+pub struct Frame {
+    /// Position of the frame in export order.
+    pub index: usize,
+    /// Name of the frame in the export, e.g. `demons.aseprite`.
+    pub filename: String,
+    /// `filename` split into sprite title, layer and frame number.
+    pub name: FrameName,
+    /// Packed rectangle in the atlas image, including inner padding. For
+    /// rotated frames this is the unrotated size; see [`Frame::source_rect`].
+    pub rect: Rect,
+    /// The packer stored the frame turned 90 degrees clockwise.
+    pub rotated: bool,
+    pub slices: Vec<Slice>,
+    /// Display time in milliseconds.
+    pub duration: i32,
+    /// Size of the sprite before trimming.
+    pub source_size: AsepriteSize,
+    /// Where the trimmed pixels sit inside `source_size`.
+    pub sprite_source_size: Rect,
+    /// Index into [`ImageAtlas::pages`] of the image holding the frame.
+    pub page: usize
+}
+
+impl Frame {
+    /// Where the frame's packed pixels sit in the atlas texture. Rotated
+    /// frames occupy a rect with width and height swapped.
+    pub fn source_rect(&self) -> IRect {
+        let size = if self.rotated {
+            ivec2(self.rect.h, self.rect.w)
+        } else {
+            ivec2(self.rect.w, self.rect.h)
+        };
+
+        return IRect { offset: ivec2(self.rect.x, self.rect.y), size };
+    }
+}
+
+/// Index of a tag in the atlas that issued it. Handles are only meaningful
+/// for that atlas; using one with another atlas may panic.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TagHandle(usize);
+
+impl TagHandle {
+    /// Position of the tag in the export's `frameTags`.
+    pub fn index(&self) -> usize {
+        return self.0;
+    }
+}
+
+/// Everything in the atlas JSON that does not need the engine.
+pub struct AtlasData {
+    pub frames: Vec<Frame>,
+    /// Tags in export order; a [`TagHandle`] indexes this list.
+    pub tags: Vec<Tag>,
+    pub tag_handles: HashMap<String, TagHandle>,
+    pub layers: Vec<Layer>,
+    pub size: Vec2,
+    /// File name of the packed image, relative to the JSON file.
+    pub image: String
+}
+
+/// Per-pixel alpha of the atlas image, used for pixel-accurate hit tests.
+pub struct AlphaMask {
+    pub width: u32,
+    pub height: u32,
+    pub alpha: Vec<u8>
+}
+
+/// One packed image of an atlas.
+pub struct AtlasPage {
+    pub texture_id: TextureHandle,
+    pub size: Vec2,
+    pub alpha_mask: Option<AlphaMask>
+}
+
+/// One or more exports sharing a single frame, tag and layer namespace. Each
+/// export keeps its own image as a page; see [`ImageAtlas::add_page`].
+pub struct ImageAtlas {
+    pub pages: Vec<AtlasPage>,
+    /// Frames of every page; a frame's `index` is its position here.
+    pub frames: Vec<Frame>,
+    /// Tags of every page; a [`TagHandle`] indexes this list.
+    pub tags: Vec<Tag>,
+    pub tag_handles: HashMap<String, TagHandle>,
+    pub layers: Vec<Layer>
+}
+
+impl ImageAtlas {
+    pub fn from_data(data: AtlasData, texture_id: TextureHandle, alpha_mask: Option<AlphaMask>) -> ImageAtlas {
+        return ImageAtlas {
+            pages: vec![AtlasPage { texture_id, size: data.size, alpha_mask }],
+            frames: data.frames,
+            tags: data.tags,
+            tag_handles: data.tag_handles,
+            layers: data.layers
+        };
+    }
+
+    /// Appends another export as a new page. Its frames are renumbered to
+    /// follow the existing ones and its tags get new handles. A tag whose
+    /// name is already taken replaces the old one for name lookups, though
+    /// handles issued earlier still reach the old tag. Returns the names of
+    /// the replaced tags.
+    pub fn add_page(&mut self, data: AtlasData, texture_id: TextureHandle, alpha_mask: Option<AlphaMask>) -> Vec<String> {
+        let page = self.pages.len();
+        let offset = self.frames.len();
+        self.pages.push(AtlasPage { texture_id, size: data.size, alpha_mask });
+
+        for mut f in data.frames {
+            f.index += offset;
+            f.page = page;
+            self.frames.push(f);
+        }
+
+        let mut replaced = vec![];
+        for mut t in data.tags {
+            t.from += offset as i32;
+            t.to += offset as i32;
+            let handle = TagHandle(self.tags.len());
+            if self.tag_handles.insert(t.name.clone(), handle).is_some() {
+                replaced.push(t.name.clone());
+            }
+            self.tags.push(t);
+        }
+
+        for mut l in data.layers {
+            for c in l.cels.iter_mut() {
+                c.frame += offset;
+            }
+            self.layers.push(l);
+        }
+
+        return replaced;
+    }
+
+    /// Looks a tag up by name. Do this once and keep the handle.
+    pub fn tag_handle(&self, name: &str) -> Option<TagHandle> {
+        return self.tag_handles.get(name).copied();
+    }
+
+    pub fn tag(&self, handle: TagHandle) -> &Tag {
+        return &self.tags[handle.0];
+    }
+
+    /// The tag's frames in export order, ignoring its direction.
+    pub fn tag_frames(&self, handle: TagHandle) -> &[Frame] {
+        let tag = self.tag(handle);
+        let to = (tag.to.max(0) as usize + 1).min(self.frames.len());
+        let from = (tag.from.max(0) as usize).min(to);
+        return &self.frames[from..to];
+    }
+
+    /// Durations of the tag's frames in export order, in seconds.
+    pub fn tag_durations(&self, handle: TagHandle) -> impl Iterator<Item = f32> + '_ {
+        return self.tag_frames(handle).iter().map(|f| f.duration as f32 / 1000.0);
+    }
+
+    pub fn first_frame(&self, handle: TagHandle) -> Option<&Frame> {
+        return self.tag_frames(handle).first();
+    }
+
+    /// All frames of a tag in playback order. Ping-pong tags repeat their
+    /// inner frames backwards so cycling the list loops without a visible jump.
+    pub fn playback_frames(&self, handle: TagHandle) -> Vec<&Frame> {
+        let mut frames = self.tag_frames(handle).iter().collect_vec();
+        match self.tag(handle).direction.as_str() {
+            "reverse" => frames.reverse(),
+            "pingpong" => {
+                let back = frames.iter().rev().skip(1).take(frames.len().saturating_sub(2)).cloned().collect_vec();
+                frames.extend(back);
+            },
+            _ => {}
+        }
+
+        return frames;
+    }
+
+    /// Events of a tag in frame order, from the tag's own user data and from
+    /// the user data of cels inside the tag's frame range.
+    pub fn frame_events(&self, handle: TagHandle) -> Vec<FrameEvent> {
+        let tag = self.tag(handle);
+
+        let mut events = vec![];
+        for e in tag.data["events"].as_array().into_iter().flatten() {
+            let offset = e["frame"].as_u64().unwrap_or(0) as i32;
+            match e["event"].as_str() {
+                None => warn!("Tag {} has an event without a name: {}", tag.name, e),
+                Some(n) => events.push(FrameEvent {
+                    frame: (tag.from + offset).min(tag.to) as usize,
+                    name: n.to_string(),
+                    data: e.clone()
+                })
+            }
+        }
+
+        for layer in self.layers.iter() {
+            for cel in layer.cels.iter() {
+                let is_inside = tag.from as usize <= cel.frame && cel.frame <= tag.to as usize;
+                if let (true, Some(n)) = (is_inside, cel.data["event"].as_str()) {
+                    events.push(FrameEvent { frame: cel.frame, name: n.to_string(), data: cel.data.clone() });
+                }
+            }
+        }
+
+        events.sort_by_key(|e| e.frame);
+        return events;
+    }
+}
+
+fn is_frame_covered(frame_index: i32, ase: &AspriteSliceInstance) -> bool {
+    return ase.frame <= frame_index;
+}
+
+fn parse_user_data(owner: &str, data: &Option<String>) -> Value {
+    let raw = match data {
+        None => return Value::Object(Default::default()),
+        Some(s) => s
+    };
+
+    trace!("Processing user data of {}: {}", owner, raw);
+    return match serde_json::from_str(raw) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("{} has unreadable user data {:?}: {}", owner, raw, e);
+            Value::Object(Default::default())
+        }
+    };
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`, the forms Aseprite writes for tag and
+/// slice colours. The leading `#` is optional.
+pub fn parse_hex_color(hex: &str) -> Option<Color> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+        return None;
+    }
+
+    let mut channels = [255u8; 4];
+    for (i, c) in channels.iter_mut().enumerate().take(digits.len() / 2) {
+        *c = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).ok()?;
+    }
+
+    let [r, g, b, a] = channels.map(|c| c as f32 / 255.0);
+    return Some(Color::new(r, g, b, a));
+}
+
+fn parse_color(owner: &str, color: &Option<String>) -> Color {
+    let hex = match color {
+        None => return WHITE,
+        Some(c) => c
+    };
+
+    return match parse_hex_color(hex) {
+        Some(c) => c,
+        None => {
+            warn!("{} has unreadable colour {:?}", owner, hex);
+            WHITE
+        }
+    };
+}
+
+fn tag_from_export(tag: &AsepriteFrametag) -> Tag {
+    let owner = format!("Tag {}", tag.name);
+    return Tag {
+        name: tag.name.clone(),
+        from: tag.from,
+        to: tag.to,
+        direction: tag.direction.clone(),
+        color: parse_color(&owner, &tag.color),
+        data: parse_user_data(&owner, &tag.data)
+    };
+}
+
+/// Splits a frame filename into its title, layer and frame number. Names
+/// that follow none of Aseprite's default formats become the title as-is.
+pub fn parse_frame_filename(filename: &str) -> FrameName {
+    let stem = match filename.rfind('.') {
+        Some(i) if 0 < i => &filename[..i],
+        _ => filename
+    };
+
+    let (rest, frame) = match stem.rsplit_once(' ') {
+        Some((r, n)) if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => (r, n.parse().ok()),
+        _ => (stem, None)
+    };
+
+    let layer_start = if rest.ends_with(')') { rest.rfind(" (") } else { None };
+    let (title, layer) = match layer_start {
+        Some(i) => (&rest[..i], Some(rest[i + 2..rest.len() - 1].to_string())),
+        None => (rest, None)
+    };
+
+    return FrameName { title: title.to_string(), layer, frame };
+}
+
+fn layer_from_export(layer: &AsepriteLayer) -> Layer {
+    return Layer {
+        name: layer.name.clone(),
+        group: layer.group.clone(),
+        opacity: layer.opacity as f32 / 255.0,
+        blend_mode: layer.blend_mode.clone(),
+        data: parse_user_data(&format!("Layer {}", layer.name), &layer.data),
+        cels: layer.cels.iter().filter(|c| c.data.is_some()).map(|c| Cel {
+            frame: c.frame.max(0) as usize,
+            data: parse_user_data(&format!("Cel {}:{}", layer.name, c.frame), &c.data)
+        }).collect_vec()
+    };
+}
+
+fn find_single_slice_in_frames(frame_index: i32, slice: &AsepriteSlice) -> Option<Slice> {
+    if frame_index < slice.from || slice.to < frame_index {
+        return None;
+    }
+
+    let s = slice.keys.iter().rev().find_or_last(|&x| is_frame_covered(frame_index, x))?;
+
+    let owner = format!("Slice {}", slice.name);
+    return Some(Slice {
+        bound: s.bounds,
+        data: parse_user_data(&owner, &slice.data),
+        color: parse_color(&owner, &slice.color),
+        name: slice.name.clone()
+    })
+}
+
+fn find_slices_in_frame(frame_index: i32, slices: &Vec<AsepriteSlice>) -> Vec<Slice> {
+    return slices
+        .iter()
+        .filter_map(|s| find_single_slice_in_frames(frame_index, s))
+        .collect_vec();
+}
+
+fn log_frame_slices(frame_index: usize, slices: &Vec<Slice>) {
+    for s in slices.iter() {
+        debug!(
+            "Frame {} slice {} [{}, {}, {}, {}] = {}",
+            frame_index, s.name,
+            s.bound.x, s.bound.y, s.bound.w, s.bound.h,
+            s.data
+        );
+    }
+}
+
+/// Parses an Aseprite json-array or json-hash export.
+pub fn parse_atlas_json(json_bytes: &[u8]) -> serde_json::Result<AtlasData> {
+    let root: AsepriteAtlas = serde_json::from_slice(json_bytes)?;
+
+    let mut frames: Vec<Frame> = vec![];
+    for (frame_index, frame) in root.frames.iter().enumerate() {
+        let slices = find_slices_in_frame(frame_index as i32, &root.meta.slices);
+        log_frame_slices(frame_index, &slices);
+        frames.push(Frame {
+            index: frame_index,
+            filename: frame.filename.clone(),
+            name: parse_frame_filename(&frame.filename),
+            duration: frame.duration,
+            rect: frame.frame,
+            rotated: frame.rotated,
+            slices,
+            source_size: frame.source_size.clone(),
+            sprite_source_size: frame.sprite_source_size,
+            page: 0
+        });
+    }
+
+    let tags = root.meta.frame_tags.iter().map(tag_from_export).collect_vec();
+    let mut tag_handles: HashMap<String, TagHandle> = HashMap::new();
+    for (i, t) in tags.iter().enumerate() {
+        tag_handles.insert(t.name.clone(), TagHandle(i));
+    }
+
+    return Ok(AtlasData {
+        frames,
+        tags,
+        tag_handles,
+        layers: root.meta.layers.iter().map(layer_from_export).collect_vec(),
+        size: vec2(root.meta.size.w as f32, root.meta.size.h as f32),
+        image: root.meta.image
+    });
+}
+
+/// Describes every user data field and colour that [`parse_atlas_json`]
+/// could not read and replaced with a default, e.g.
+/// `Slice web has unreadable user data "{ pickup: web }": ...`. The game only
+/// logs these; tools can use this to report them.
+pub fn find_unreadable_fields(json_bytes: &[u8]) -> serde_json::Result<Vec<String>> {
+    let root: AsepriteAtlas = serde_json::from_slice(json_bytes)?;
+
+    let mut owners: Vec<(String, &Option<String>, &Option<String>)> = vec![];
+    for t in root.meta.frame_tags.iter() {
+        owners.push((format!("Tag {}", t.name), &t.data, &t.color));
+    }
+    for s in root.meta.slices.iter() {
+        owners.push((format!("Slice {}", s.name), &s.data, &s.color));
+    }
+    for l in root.meta.layers.iter() {
+        owners.push((format!("Layer {}", l.name), &l.data, &None));
+        for c in l.cels.iter() {
+            owners.push((format!("Cel {}:{}", l.name, c.frame), &c.data, &None));
+        }
+    }
+
+    let mut problems = vec![];
+    for (owner, data, color) in owners {
+        if let Some(raw) = data {
+            if let Err(e) = serde_json::from_str::<Value>(raw) {
+                problems.push(format!("{} has unreadable user data {:?}: {}", owner, raw, e));
+            }
+        }
+        if let Some(hex) = color {
+            if parse_hex_color(hex).is_none() {
+                problems.push(format!("{} has unreadable colour {:?}", owner, hex));
+            }
+        }
+    }
+
+    return Ok(problems);
+}
+
+/// Decodes the alpha channel of a PNG. Returns `None` if it cannot be decoded.
+pub fn decode_alpha_mask(png_bytes: &[u8]) -> Option<AlphaMask> {
+    let image = match image::load_from_memory_with_format(png_bytes, image::ImageFormat::Png) {
+        Err(_) => return None,
+        Ok(i) => i.to_rgba8()
+    };
+
+    return Some(AlphaMask {
+        width: image.width(),
+        height: image.height(),
+        alpha: image.pixels().map(|p| p.0[3]).collect_vec()
+    });
+}
+
+/// Alpha of the frame's pixel at `local`, given in source-size pixel
+/// coordinates with the origin in the top-left corner. Trimmed-away pixels and
+/// atlases without a decoded mask report fully transparent.
+pub fn frame_alpha_at(atlas: &ImageAtlas, frame: &Frame, local: IVec2) -> u8 {
+    let mask = match &atlas.pages[frame.page].alpha_mask {
+        None => return 0,
+        Some(m) => m
+    };
+
+    let sx = local.x - frame.sprite_source_size.x;
+    let sy = local.y - frame.sprite_source_size.y;
+    let is_outside = sx < 0 || sy < 0
+        || frame.sprite_source_size.w <= sx || frame.sprite_source_size.h <= sy;
+    if is_outside {
+        return 0;
+    }
+
+    // Packed rects include the inner padding around the trimmed sprite.
+    let pad_x = (frame.rect.w - frame.sprite_source_size.w) / 2;
+    let pad_y = (frame.rect.h - frame.sprite_source_size.h) / 2;
+    let ux = pad_x + sx;
+    let uy = pad_y + sy;
+    let (ax, ay) = if frame.rotated {
+        // Turning clockwise moves the left column to the top row.
+        ((frame.rect.x + frame.rect.h - 1 - uy) as u32, (frame.rect.y + ux) as u32)
+    } else {
+        ((frame.rect.x + ux) as u32, (frame.rect.y + uy) as u32)
+    };
+    if mask.width <= ax || mask.height <= ay {
+        return 0;
+    }
+
+    return mask.alpha[(ay * mask.width + ax) as usize];
+}
+
+pub fn find_first_frame_in_tag<'a>(atlas: &'a ImageAtlas, name: &str) -> Option<&'a Frame> {
+    return atlas.first_frame(atlas.tag_handle(name)?);
+}
+
+/// [`ImageAtlas::playback_frames`] by tag name; empty for unknown tags.
+pub fn frames_in_tag<'a>(atlas: &'a ImageAtlas, name: &str) -> Vec<&'a Frame> {
+    return atlas.tag_handle(name).map_or(vec![], |h| atlas.playback_frames(h));
+}
+
+/// [`ImageAtlas::frame_events`] by tag name; empty for unknown tags.
+pub fn tag_events(atlas: &ImageAtlas, name: &str) -> Vec<FrameEvent> {
+    return atlas.tag_handle(name).map_or(vec![], |h| atlas.frame_events(h));
+}
+
+pub fn find_layer<'a>(atlas: &'a ImageAtlas, name: &str) -> Option<&'a Layer> {
+    return atlas.layers.iter().find(|l| l.name == name);
+}
+
+/// The frame holding `layer` for the same sprite and frame number as `frame`,
+/// in a `--split-layers` export.
+pub fn frame_in_layer<'a>(atlas: &'a ImageAtlas, frame: &Frame, layer: &str) -> Option<&'a Frame> {
+    return atlas.frames.iter().find(|f| {
+        f.page == frame.page
+            && f.name.title == frame.name.title
+            && f.name.frame == frame.name.frame
+            && f.name.layer.as_deref() == Some(layer)
+    });
+}
+
+/// Draws a frame so its untrimmed source rectangle is centred on `pos`.
+pub fn draw_frame(atlas: &ImageAtlas, frame: &Frame, pos: Vec2, z_index: i32) {
+    draw_frame_tinted(atlas, frame, pos, WHITE, z_index);
+}
+
+/// [`draw_frame`] with the texture multiplied by `color`, e.g. to apply a
+/// layer's opacity.
+pub fn draw_frame_tinted(atlas: &ImageAtlas, frame: &Frame, pos: Vec2, color: Color, z_index: i32) {
+    let packed = frame.source_rect().size;
+    let params = DrawTextureProParams {
+        source_rect: Some(frame.source_rect()),
+        size: vec2(packed.x as f32, packed.y as f32),
+        // Undo the packer's clockwise turn.
+        rotation: if frame.rotated { std::f32::consts::FRAC_PI_2 } else { 0.0 },
+        ..Default::default()
+    };
+
+    let sprite_pos = vec2(
+        frame.sprite_source_size.x as f32,
+        -frame.sprite_source_size.y as f32,
+    );
+    let offset = vec2(
+        -( -frame.sprite_source_size.w + frame.source_size.w) as f32 * 0.5,
+        -( frame.sprite_source_size.h - frame.source_size.h) as f32 * 0.5
+    );
+    draw_sprite_pro(
+        atlas.pages[frame.page].texture_id,
+        pos + offset + sprite_pos,
+        color, z_index, params
+    );
+}
+
+/// Loads an export from disk. The image is found next to the JSON file using
+/// the name recorded in its meta block. Panics if either file is unreadable.
+pub fn load_aseprite_atlas(c: &mut EngineContext, json_path: &Path) -> ImageAtlas {
+    info!("Loading {}", json_path.display());
+    let json_bytes = std::fs::read(json_path).unwrap();
+    let data = parse_atlas_json(&json_bytes).unwrap();
+
+    let image_path = json_path.parent().unwrap().join(&data.image);
+    info!("Loading image file {}", image_path.display());
+    let image_bytes = std::fs::read(image_path).unwrap();
+    c.load_texture_from_bytes("atlas", image_bytes.as_slice());
+
+    return ImageAtlas::from_data(data, texture_id("atlas"), decode_alpha_mask(&image_bytes));
+}
+
+/// Loads an export from memory, e.g. from `include_bytes!`. The texture is
+/// registered with comfy as `"atlas"`. Panics if the JSON is malformed.
+pub fn load_aseprite_atlas_bytes(
+        c: &mut EngineContext,
+        json_bytes: &[u8],
+        png_bytes: &[u8]
+) -> ImageAtlas {
+    let data = parse_atlas_json(json_bytes).unwrap();
+    c.load_texture_from_bytes("atlas", png_bytes);
+
+    return ImageAtlas::from_data(data, texture_id("atlas"), decode_alpha_mask(png_bytes));
+}
+
+/// Loads another export from disk into `atlas` as a new page, registering
+/// its image with comfy as `texture`. Returns the names of tags it replaced,
+/// see [`ImageAtlas::add_page`].
+pub fn load_atlas_page(
+        c: &mut EngineContext,
+        atlas: &mut ImageAtlas,
+        texture: &str,
+        json_path: &Path
+) -> Result<Vec<String>, String> {
+    info!("Loading {}", json_path.display());
+    let json_bytes = std::fs::read(json_path).map_err(|e| format!("{}: {}", json_path.display(), e))?;
+    let data = parse_atlas_json(&json_bytes).map_err(|e| format!("{}: {}", json_path.display(), e))?;
+
+    let image_path = json_path.parent().unwrap_or(Path::new(".")).join(&data.image);
+    let image_bytes = std::fs::read(&image_path).map_err(|e| format!("{}: {}", image_path.display(), e))?;
+    c.load_texture_from_bytes(texture, image_bytes.as_slice());
+
+    return Ok(atlas.add_page(data, texture_id(texture), decode_alpha_mask(&image_bytes)));
+}
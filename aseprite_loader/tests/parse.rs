@@ -0,0 +1,312 @@
+use aseprite_loader::*;
+use comfy::*;
+
+const SAMPLE: &[u8] = include_bytes!("data/sample_atlas.json");
+
+fn sample_atlas() -> ImageAtlas {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+    return ImageAtlas::from_data(data, texture_id("atlas"), None);
+}
+
+#[test]
+fn parses_frames_and_meta() {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+
+    assert_eq!(data.frames.len(), 4);
+    assert_eq!(data.size, vec2(52.0, 20.0));
+    assert_eq!(data.image, "sample_atlas.png");
+    assert_eq!(data.frames[1].index, 1);
+    assert_eq!(data.frames[1].duration, 50);
+    assert_eq!(data.frames[1].rect, Rect { x: 34, y: 0, w: 6, h: 6 });
+    assert_eq!(data.frames[1].sprite_source_size, Rect { x: 1, y: 1, w: 4, h: 4 });
+    assert_eq!(data.frames[1].source_size, AsepriteSize { w: 8, h: 8 });
+}
+
+#[test]
+fn rejects_malformed_json() {
+    assert!(parse_atlas_json(b"{ \"frames\": 3 }").is_err());
+}
+
+#[test]
+fn resolves_slices_and_user_data() {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+    let level = &data.frames[0];
+
+    let skull = level.slices.iter().find(|s| s.name == "skull").unwrap();
+    assert_eq!(skull.bound, Rect { x: 4, y: 5, w: 6, h: 7 });
+    assert_eq!(skull.data["pickup"], "skull");
+
+    // Unreadable user data degrades to an empty object instead of panicking.
+    let broken = level.slices.iter().find(|s| s.name == "broken").unwrap();
+    assert!(broken.data.as_object().unwrap().is_empty());
+
+    assert!(level.slices.iter().all(|s| s.name != "moving"));
+}
+
+#[test]
+fn slice_keys_apply_from_their_frame_onwards() {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+    let bound_at = |i: usize| data.frames[i].slices.iter()
+        .find(|s| s.name == "moving")
+        .map(|s| s.bound);
+
+    assert_eq!(bound_at(1), Some(Rect { x: 0, y: 0, w: 1, h: 1 }));
+    assert_eq!(bound_at(2), Some(Rect { x: 1, y: 1, w: 1, h: 1 }));
+    assert_eq!(bound_at(3), Some(Rect { x: 1, y: 1, w: 1, h: 1 }));
+}
+
+#[test]
+fn looks_up_tags() {
+    let atlas = sample_atlas();
+
+    let first = find_first_frame_in_tag(&atlas, "spark/spark").unwrap();
+    assert_eq!(first.index, 1);
+    assert!(find_first_frame_in_tag(&atlas, "missing").is_none());
+    assert!(frames_in_tag(&atlas, "missing").is_empty());
+}
+
+#[test]
+fn orders_tag_frames_by_direction() {
+    let atlas = sample_atlas();
+    let indices = |tag: &str| frames_in_tag(&atlas, tag).iter().map(|f| f.index).collect::<Vec<_>>();
+
+    assert_eq!(indices("level/level"), vec![0]);
+    assert_eq!(indices("spark/spark"), vec![1, 2, 3, 2]);
+    assert_eq!(indices("spark/reverse"), vec![3, 2, 1]);
+}
+
+#[test]
+fn source_rect_matches_packed_rect() {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+    let rect = data.frames[2].source_rect();
+
+    assert_eq!(rect.offset, ivec2(40, 0));
+    assert_eq!(rect.size, ivec2(6, 6));
+}
+
+#[test]
+fn alpha_lookup_without_mask_is_transparent() {
+    let atlas = sample_atlas();
+    assert_eq!(frame_alpha_at(&atlas, &atlas.frames[0], ivec2(3, 3)), 0);
+}
+
+#[test]
+fn alpha_lookup_skips_inner_padding() {
+    // One opaque pixel at the top-left of the trimmed spark in frame 1, which
+    // is packed at (34, 0) with one pixel of padding.
+    let mut image = image::RgbaImage::new(52, 20);
+    image.put_pixel(35, 1, image::Rgba([255, 255, 255, 255]));
+    let mut png = vec![];
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+
+    let data = parse_atlas_json(SAMPLE).unwrap();
+    let atlas = ImageAtlas::from_data(data, texture_id("atlas"), decode_alpha_mask(&png));
+    let frame = &atlas.frames[1];
+
+    assert_eq!(frame_alpha_at(&atlas, frame, ivec2(1, 1)), 255);
+    assert_eq!(frame_alpha_at(&atlas, frame, ivec2(2, 1)), 0);
+    // Outside the trimmed area.
+    assert_eq!(frame_alpha_at(&atlas, frame, ivec2(0, 0)), 0);
+}
+
+#[test]
+fn json_hash_matches_json_array() {
+    let array = parse_atlas_json(SAMPLE).unwrap();
+    let hash = parse_atlas_json(include_bytes!("data/sample_atlas_hash.json")).unwrap();
+
+    assert_eq!(hash.frames.len(), array.frames.len());
+    for (a, h) in array.frames.iter().zip(hash.frames.iter()) {
+        assert_eq!(a.index, h.index);
+        assert_eq!(a.rect, h.rect);
+        assert_eq!(a.duration, h.duration);
+        assert_eq!(a.slices.len(), h.slices.len());
+    }
+    assert_eq!(hash.frames[2].filename, "spark 2.aseprite");
+}
+
+#[test]
+fn loads_texture_packer_style_sheets() {
+    let data = parse_atlas_json(include_bytes!("data/texture_packer.json")).unwrap();
+
+    assert_eq!(data.frames.len(), 1);
+    assert!(data.tags.is_empty());
+    assert_eq!(data.frames[0].filename, "wide.png");
+    assert_eq!(data.frames[0].duration, 100);
+    assert!(data.frames[0].rotated);
+}
+
+#[test]
+fn rotated_frames_swap_their_packed_size() {
+    let data = parse_atlas_json(include_bytes!("data/texture_packer.json")).unwrap();
+    let rect = data.frames[0].source_rect();
+
+    assert_eq!(rect.offset, ivec2(0, 0));
+    assert_eq!(rect.size, ivec2(2, 4));
+}
+
+#[test]
+fn alpha_lookup_follows_rotation() {
+    // The 4x2 sprite is stored turned clockwise in a 2x4 image, so its
+    // top-left pixel lands in the top-right corner.
+    let mut image = image::RgbaImage::new(2, 4);
+    image.put_pixel(1, 0, image::Rgba([255, 255, 255, 255]));
+    let mut png = vec![];
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+
+    let data = parse_atlas_json(include_bytes!("data/texture_packer.json")).unwrap();
+    let atlas = ImageAtlas::from_data(data, texture_id("atlas"), decode_alpha_mask(&png));
+    let frame = &atlas.frames[0];
+
+    assert_eq!(frame_alpha_at(&atlas, frame, ivec2(0, 0)), 255);
+    assert_eq!(frame_alpha_at(&atlas, frame, ivec2(1, 0)), 0);
+    assert_eq!(frame_alpha_at(&atlas, frame, ivec2(0, 1)), 0);
+}
+
+#[test]
+fn parses_layers() {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+
+    assert_eq!(data.layers.len(), 3);
+    let glow = &data.layers[1];
+    assert_eq!(glow.name, "glow");
+    assert!((glow.opacity - 128.0 / 255.0).abs() < 1e-6);
+    assert_eq!(glow.blend_mode, "addition");
+    assert_eq!(glow.data["toggle"], true);
+    assert_eq!(data.layers[2].group.as_deref(), Some("debug"));
+    assert!(data.layers[0].data.as_object().unwrap().is_empty());
+}
+
+#[test]
+fn decodes_frame_filenames() {
+    let name = |title: &str, layer: Option<&str>, frame: Option<usize>| FrameName {
+        title: title.to_string(),
+        layer: layer.map(|l| l.to_string()),
+        frame
+    };
+
+    assert_eq!(parse_frame_filename("background.aseprite"), name("background", None, None));
+    assert_eq!(parse_frame_filename("spark 2.aseprite"), name("spark", None, Some(2)));
+    assert_eq!(parse_frame_filename("circle (glow) 1.aseprite"), name("circle", Some("glow"), Some(1)));
+    assert_eq!(parse_frame_filename("big demon (hit box) 12.png"), name("big demon", Some("hit box"), Some(12)));
+    assert_eq!(parse_frame_filename("odd name"), name("odd name", None, None));
+}
+
+#[test]
+fn finds_the_matching_frame_in_another_layer() {
+    let data = parse_atlas_json(include_bytes!("data/split_layers.json")).unwrap();
+    let atlas = ImageAtlas::from_data(data, texture_id("atlas"), None);
+
+    let base = &atlas.frames[2];
+    let glow = frame_in_layer(&atlas, base, "glow").unwrap();
+    assert_eq!(glow.index, 3);
+    assert!(frame_in_layer(&atlas, base, "missing").is_none());
+    assert_eq!(find_layer(&atlas, "glow").unwrap().blend_mode, "screen");
+}
+
+#[test]
+fn parses_hex_colours() {
+    let c = parse_hex_color("#ff800040").unwrap();
+    assert_eq!((c.r, c.g, c.b), (1.0, 128.0 / 255.0, 0.0));
+    assert!((c.a - 64.0 / 255.0).abs() < 1e-6);
+
+    assert_eq!(parse_hex_color("0000ff").unwrap().a, 1.0);
+    assert!(parse_hex_color("#12345").is_none());
+    assert!(parse_hex_color("#gg0000").is_none());
+}
+
+#[test]
+fn exposes_tag_and_slice_colours_and_data() {
+    let atlas = sample_atlas();
+
+    let spark = atlas.tag(atlas.tag_handle("spark/spark").unwrap());
+    assert_eq!(spark.color.r, 254.0 / 255.0);
+    assert_eq!(spark.color.a, 0.0);
+    assert_eq!(spark.data["loop"], true);
+    assert_eq!(spark.data["events"][0]["event"], "spawn_sparks");
+
+    // Tags without colour or data fall back to white and an empty object.
+    let level = atlas.tag(atlas.tag_handle("level/level").unwrap());
+    assert_eq!(level.color.a, 1.0);
+    assert!(level.data.as_object().unwrap().is_empty());
+
+    let skull = atlas.frames[0].slices.iter().find(|s| s.name == "skull").unwrap();
+    assert_eq!((skull.color.r, skull.color.g, skull.color.b, skull.color.a), (0.0, 0.0, 1.0, 1.0));
+}
+
+#[test]
+fn collects_tag_and_cel_events_in_frame_order() {
+    let atlas = sample_atlas();
+    let events = tag_events(&atlas, "spark/spark");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!((events[0].frame, events[0].name.as_str()), (3, "spawn_sparks"));
+    assert_eq!((events[1].frame, events[1].name.as_str()), (3, "flash"));
+    assert_eq!(events[1].data["sound"], "zap");
+
+    assert!(tag_events(&atlas, "level/level").is_empty());
+    assert!(tag_events(&atlas, "missing").is_empty());
+}
+
+#[test]
+fn lists_only_cels_with_user_data() {
+    let data = parse_atlas_json(SAMPLE).unwrap();
+    let glow = &data.layers[1];
+
+    assert_eq!(glow.cels.len(), 1);
+    assert_eq!(glow.cels[0].frame, 3);
+}
+
+#[test]
+fn tag_handles_index_frames_and_durations() {
+    let atlas = sample_atlas();
+    let spark = atlas.tag_handle("spark/spark").unwrap();
+
+    assert_eq!(spark.index(), 1);
+    assert_eq!(atlas.tag(spark).name, "spark/spark");
+    assert_eq!(atlas.tag_frames(spark).iter().map(|f| f.index).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(atlas.tag_durations(spark).collect::<Vec<_>>(), vec![0.05, 0.06, 0.07]);
+    assert_eq!(atlas.first_frame(spark).unwrap().index, 1);
+    assert!(atlas.tag_handle("missing").is_none());
+}
+
+#[test]
+fn reports_unreadable_user_data() {
+    let problems = find_unreadable_fields(SAMPLE).unwrap();
+
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("Slice broken has unreadable user data"));
+    assert!(find_unreadable_fields(b"{ \"frames\": 3 }").is_err());
+}
+
+#[test]
+fn added_pages_renumber_frames_and_tags() {
+    let mut atlas = sample_atlas();
+    let old_spark = atlas.tag_handle("spark/spark").unwrap();
+
+    let replaced = atlas.add_page(parse_atlas_json(SAMPLE).unwrap(), texture_id("mod"), None);
+    assert_eq!(replaced, vec!["level/level", "spark/spark", "spark/reverse"]);
+    assert_eq!(atlas.pages.len(), 2);
+    assert_eq!(atlas.frames.len(), 8);
+    assert_eq!((atlas.frames[5].index, atlas.frames[5].page), (5, 1));
+
+    // Names now reach the new page; older handles keep their tag.
+    let spark = atlas.tag_handle("spark/spark").unwrap();
+    assert_ne!(spark, old_spark);
+    assert_eq!(atlas.tag_frames(spark).iter().map(|f| f.index).collect::<Vec<_>>(), vec![5, 6, 7]);
+    assert_eq!(atlas.first_frame(old_spark).unwrap().page, 0);
+
+    let events = atlas.frame_events(spark);
+    assert_eq!(events.iter().map(|e| e.frame).collect::<Vec<_>>(), vec![7, 7]);
+}
+
+#[test]
+fn summon_animation_fires_spawn_demon_at_its_peak() {
+    let data = parse_atlas_json(include_bytes!("data/summon_events.json")).unwrap();
+    let atlas = ImageAtlas::from_data(data, texture_id("summon"), None);
+    let summon = atlas.tag_handle("summon/summon").unwrap();
+
+    let events = atlas.frame_events(summon);
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].frame, events[0].name.as_str()), (2, "spawn_demon"));
+    assert_eq!(atlas.playback_frames(summon).len(), 4);
+}
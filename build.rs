@@ -0,0 +1,168 @@
+// Generates `atlas_names.rs` with a constant for every tag and slice name in
+// the exported atlas, so a renamed Aseprite tag breaks the build instead of
+// panicking at runtime. Without the `embedded_assets` feature a missing
+// export only warns, and the tags the game names are generated unchecked.
+//
+// With `embedded_assets` it also generates `embedded_assets.rs`, the table of
+// files compiled into the binary: the atlas, everything under assets/data and
+// the sound files the recipe data names. A named sound that is missing fails
+// the build there, and only warns when assets are loaded at runtime.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const ATLAS_JSON: &str = "target/atlas.json";
+const ATLAS_PNG: &str = "target/atlas.png";
+const DATA_DIR: &str = "assets/data";
+const RECIPES_JSON: &str = "assets/data/recipes.json";
+
+/// Tags the game names through `atlas_names::tags`, generated as-is when
+/// there is no export to read them from.
+const GAME_TAGS: [&str; 4] = ["background/background", "book/book", "no/win", "no/no"];
+
+fn constant_name(name: &str) -> String {
+    let mut out: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+
+    return out;
+}
+
+fn names(root: &serde_json::Value, section: &str) -> Vec<String> {
+    return root["meta"][section].as_array()
+        .map(|a| a.iter().filter_map(|v| v["name"].as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+}
+
+fn write_module(out: &mut String, module: &str, doc: &str, names: Vec<String>) {
+    // Names that differ only in punctuation would collide; the first one wins.
+    let mut constants: BTreeMap<String, String> = BTreeMap::new();
+    for name in names {
+        let constant = constant_name(&name);
+        match constants.get(&constant) {
+            Some(existing) if *existing != name => println!(
+                "cargo:warning={} {:?} and {:?} both map to {}; keeping the first",
+                module, existing, name, constant
+            ),
+            Some(_) => {},
+            None => { constants.insert(constant, name); }
+        }
+    }
+
+    out.push_str(&format!("/// {}\npub mod {} {{\n", doc, module));
+    for (constant, name) in constants.iter() {
+        out.push_str(&format!("    pub const {}: &str = {:?};\n", constant, name));
+    }
+    out.push_str("}\n\n");
+}
+
+fn data_files(dir: &Path, out: &mut Vec<String>) {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Cannot read {}: {}", dir.display(), e))
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            data_files(&path, out);
+        } else {
+            out.push(path.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+/// Paths of the sound files named in the recipe data.
+fn sound_files() -> Vec<String> {
+    let bytes = fs::read(RECIPES_JSON).unwrap_or_else(|e| panic!("Cannot read {}: {}", RECIPES_JSON, e));
+    let root: serde_json::Value = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|e| panic!("{} is not valid JSON: {}", RECIPES_JSON, e));
+
+    let mut paths: Vec<String> = root["sounds"].as_object()
+        .map(|o| o.values().filter_map(|v| v["path"].as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+    paths.sort();
+    paths.dedup();
+    return paths;
+}
+
+fn write_embedded(out_dir: &Path, sounds: &Vec<String>) {
+    for required in [ATLAS_JSON, ATLAS_PNG] {
+        if !Path::new(required).exists() {
+            panic!(
+                "Cannot embed {}. Export the atlas first with `make asprite_export`, \
+                or build with --no-default-features to load assets at runtime.", required
+            );
+        }
+    }
+
+    let mut files = vec![ATLAS_JSON.to_string(), ATLAS_PNG.to_string()];
+    data_files(Path::new(DATA_DIR), &mut files);
+    files.extend(sounds.iter().cloned());
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let mut out = format!("const EMBEDDED: [(&str, &[u8]); {}] = [\n", files.len());
+    for f in files.iter() {
+        out.push_str(&format!("    ({:?}, include_bytes!({:?}) as &[u8]),\n", f, format!("{}/{}", manifest_dir, f)));
+    }
+    out.push_str("];\n");
+
+    fs::write(out_dir.join("embedded_assets.rs"), out).unwrap();
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ATLAS_JSON);
+    println!("cargo:rerun-if-changed={}", ATLAS_PNG);
+    println!("cargo:rerun-if-changed={}", DATA_DIR);
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+    let is_embedded = env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_some();
+
+    let sounds = sound_files();
+    for sound in sounds.iter() {
+        println!("cargo:rerun-if-changed={}", sound);
+        if Path::new(sound).exists() {
+            continue;
+        }
+        if is_embedded {
+            panic!("Cannot embed {}, named in {}. Add the file or remove the sound.", sound, RECIPES_JSON);
+        }
+        println!("cargo:warning=Sound {} named in {} is missing and will not play.", sound, RECIPES_JSON);
+    }
+
+    if is_embedded {
+        write_embedded(out_dir, &sounds);
+    }
+
+    let root: Option<serde_json::Value> = match fs::read(ATLAS_JSON) {
+        Ok(bytes) => Some(serde_json::from_slice(&bytes)
+            .unwrap_or_else(|e| panic!("{} is not valid JSON: {}", ATLAS_JSON, e))),
+        Err(e) if !is_embedded => {
+            println!(
+                "cargo:warning=Cannot read {}: {}. Tag names are not checked; \
+                export the atlas with `make asprite_export` before running.", ATLAS_JSON, e
+            );
+            None
+        },
+        Err(e) => panic!("Cannot read {}: {}. Export the atlas first with `make asprite_export`.", ATLAS_JSON, e)
+    };
+
+    let (tags, slices) = match &root {
+        Some(r) => (names(r, "frameTags"), names(r, "slices")),
+        None => (GAME_TAGS.iter().map(|t| t.to_string()).collect(), vec![])
+    };
+
+    let mut out = String::new();
+    write_module(&mut out, "tags", "Every frame tag in the atlas.", tags);
+    write_module(&mut out, "slices", "Every slice in the atlas.", slices);
+
+    fs::write(out_dir.join("atlas_names.rs"), out).unwrap();
+}
@@ -25,10 +25,15 @@ def patch_frame_tag(prefix, sprite_json, frame_to_index):
         from_index = fetch_global_index(sprite_json, tag["from"], frame_to_index)
         to_index = fetch_global_index(sprite_json, tag["to"], frame_to_index)
         direction = tag["direction"]
-        new_tags.append({
+        new_tag = {
             "name": name, "to": to_index, "from": from_index,
             "direction": direction
-        })
+        }
+        # Colour and user data are optional and only present when set.
+        for key in ("color", "data"):
+            if key in tag:
+                new_tag[key] = tag[key]
+        new_tags.append(new_tag)
 
     frames = sprite_json["frames"]
     first_frame = frames[0]
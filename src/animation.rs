@@ -0,0 +1,139 @@
+use comfy::*;
+use aseprite_loader::{ImageAtlas, FrameEvent, TagHandle};
+
+/// Fired once when a non-looping animation reaches its last frame, or right
+/// away when its tag is missing from the atlas.
+pub const FINISHED: &str = "finished";
+
+/// Plays a tag's frames in order. The frame list and events are resolved from
+/// the atlas on the first tick, so spawning code does not need the atlas.
+pub struct Animator {
+    /// `None` when the tag is missing; the animation then finishes at once.
+    pub tag: Option<TagHandle>,
+    pub looping: bool,
+    pub frames: Vec<(usize, f32)>,
+    pub events: Vec<FrameEvent>,
+    pub position: usize,
+    pub timer: f32,
+    pub is_resolved: bool,
+    pub is_finished: bool
+}
+
+pub fn new_animator(tag: Option<TagHandle>, looping: bool) -> Animator {
+    return Animator {
+        tag,
+        looping,
+        frames: vec![],
+        events: vec![],
+        position: 0,
+        timer: 0.0,
+        is_resolved: false,
+        is_finished: false
+    };
+}
+
+impl Animator {
+    /// Atlas frame index currently shown, once resolved.
+    pub fn current_frame(&self) -> Option<usize> {
+        return self.frames.get(self.position).map(|(i, _)| *i);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationEvent {
+    pub source: Entity,
+    pub name: String,
+    pub data: serde_json::Value
+}
+
+/// Animation events for the current frame, cleared at the start of
+/// `animation_spin` like the interaction bus.
+pub struct AnimationEvents {
+    pub events: Vec<AnimationEvent>
+}
+
+pub fn new_animation_events() -> AnimationEvents {
+    return AnimationEvents { events: vec![] };
+}
+
+pub fn read() -> Vec<AnimationEvent> {
+    for (_, bus) in world().query::<&AnimationEvents>().iter() {
+        return bus.events.clone();
+    }
+
+    return vec![];
+}
+
+fn resolve(atlas: &ImageAtlas, animator: &mut Animator) {
+    animator.is_resolved = true;
+    let tag = match animator.tag {
+        None => return,
+        Some(t) => t
+    };
+
+    animator.frames = atlas.playback_frames(tag).iter()
+        .map(|f| (f.index, f.duration.max(1) as f32 / 1000.0))
+        .collect_vec();
+    animator.events = atlas.frame_events(tag);
+}
+
+fn fire_frame_events(id: Entity, animator: &Animator, out: &mut Vec<AnimationEvent>) {
+    let frame = match animator.current_frame() {
+        None => return,
+        Some(f) => f
+    };
+
+    for e in animator.events.iter().filter(|e| e.frame == frame) {
+        out.push(AnimationEvent { source: id, name: e.name.clone(), data: e.data.clone() });
+    }
+}
+
+fn finish(id: Entity, animator: &mut Animator, out: &mut Vec<AnimationEvent>) {
+    animator.is_finished = true;
+    out.push(AnimationEvent { source: id, name: FINISHED.to_string(), data: serde_json::Value::Null });
+}
+
+/// Moves one animator on by `dt`, collecting the events of every frame it
+/// reaches.
+pub fn advance(id: Entity, atlas: &ImageAtlas, animator: &mut Animator, dt: f32, out: &mut Vec<AnimationEvent>) {
+    if animator.is_finished {
+        return;
+    }
+
+    if !animator.is_resolved {
+        resolve(atlas, animator);
+        if animator.frames.is_empty() {
+            finish(id, animator, out);
+            return;
+        }
+        fire_frame_events(id, animator, out);
+    }
+
+    animator.timer += dt;
+    while animator.frames[animator.position].1 <= animator.timer {
+        animator.timer -= animator.frames[animator.position].1;
+
+        if animator.position + 1 < animator.frames.len() {
+            animator.position += 1;
+        } else if animator.looping {
+            animator.position = 0;
+        } else {
+            finish(id, animator, out);
+            break;
+        }
+        fire_frame_events(id, animator, out);
+    }
+}
+
+pub fn animation_spin(atlas: &ImageAtlas) {
+    let dt = delta();
+    let mut fired = vec![];
+
+    for (id, animator) in world_mut().query_mut::<&mut Animator>().into_iter() {
+        advance(id, atlas, animator, dt, &mut fired);
+    }
+
+    for (_, bus) in world_mut().query_mut::<&mut AnimationEvents>().into_iter() {
+        bus.events = fired.clone();
+    }
+}
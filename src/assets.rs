@@ -0,0 +1,64 @@
+//! Where the game's art and data come from. With the default
+//! `embedded_assets` feature (release and web builds) the files below are
+//! compiled into the binary. Without it (`cargo run --no-default-features`)
+//! they are read at startup from the asset directory, so exported art and
+//! edited data show up without rebuilding.
+//!
+//! Paths are relative to the repository root in both modes, e.g.
+//! `target/atlas.json`. The sounds named in `recipes.json` are embedded too,
+//! and the build fails if one is missing. Files that are not embedded, such
+//! as mod assets, are always read from disk.
+
+use std::borrow::Cow;
+use std::env;
+use std::path::PathBuf;
+
+/// Asset directory used for files that are not embedded. Defaults to the
+/// working directory.
+pub const ASSET_DIR_ENV: &str = "ASMODEUS_ASSET_DIR";
+
+pub const ATLAS_JSON: &str = "target/atlas.json";
+pub const ATLAS_PNG: &str = "target/atlas.png";
+pub const RECIPES_JSON: &str = "assets/data/recipes.json";
+pub const EFFECTS_JSON: &str = "assets/data/effects.json";
+
+// `EMBEDDED`: every embedded path with its bytes, generated by build.rs from
+// the atlas, the contents of assets/data and the sounds it names.
+#[cfg(feature = "embedded_assets")]
+include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+#[cfg(feature = "embedded_assets")]
+fn find_embedded(path: &str) -> Option<&'static [u8]> {
+    return EMBEDDED.iter().find(|(p, _)| *p == path).map(|(_, b)| *b);
+}
+
+#[cfg(not(feature = "embedded_assets"))]
+fn find_embedded(_path: &str) -> Option<&'static [u8]> {
+    return None;
+}
+
+pub fn asset_dir() -> PathBuf {
+    return PathBuf::from(env::var(ASSET_DIR_ENV).unwrap_or(".".to_string()));
+}
+
+/// Reads an asset, preferring the embedded copy.
+pub fn try_read(path: &str) -> Result<Cow<'static, [u8]>, String> {
+    if let Some(bytes) = find_embedded(path) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+
+    let full_path = asset_dir().join(path);
+    return std::fs::read(&full_path)
+        .map(Cow::Owned)
+        .map_err(|e| format!("{}: {}", full_path.display(), e));
+}
+
+/// Reads an asset the game cannot start without, and panics with a hint on
+/// how to provide it.
+pub fn read(path: &str) -> Cow<'static, [u8]> {
+    return try_read(path).unwrap_or_else(|e| panic!(
+        "Missing asset {}. Run `make asprite_export` for art, and set {} to the \
+        folder holding target/ and assets/ or build with the embedded_assets feature.",
+        e, ASSET_DIR_ENV
+    ));
+}
@@ -0,0 +1,7 @@
+//! Tag and slice names read from `target/atlas.json` at build time, e.g.
+//! `tags::PICKUPS_EYE` for `"pickups/eye"`. See `build.rs`.
+
+// Every name is generated whether or not the game uses it.
+#![allow(dead_code)]
+
+include!(concat!(env!("OUT_DIR"), "/atlas_names.rs"));
@@ -0,0 +1,177 @@
+use comfy::*;
+use log::{info, warn};
+use crate::component::{self, GlobalGameState};
+use crate::events::{self, InteractionEvent};
+use crate::recipe_data::{self, RecipeData, SoundBus};
+use crate::system;
+use crate::animation;
+use crate::assets;
+
+pub const PLAY_SOUND_EVENT: &str = "play_sound";
+
+pub struct AudioMixer {
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub loaded: Vec<(String, SoundBus)>,
+    pub current_music: Option<String>,
+    /// Volume `current_music` was started with.
+    pub playing_music_volume: f32,
+    pub was_book_open: bool
+}
+
+pub fn new_audio_mixer() -> AudioMixer {
+    return AudioMixer {
+        music_volume: 0.6,
+        sfx_volume: 1.0,
+        loaded: vec![],
+        current_music: None,
+        playing_music_volume: 0.0,
+        was_book_open: false
+    };
+}
+
+/// Loads every sound named in the recipe data. Missing files are logged and
+/// skipped so the game still runs without an audio pack.
+pub fn load_sounds(mixer: &mut AudioMixer, data: &RecipeData) {
+    for (name, def) in data.sounds.iter() {
+        match assets::try_read(&def.path) {
+            Err(e) => warn!("Sound {} not loaded: {}", name, e),
+            Ok(bytes) => {
+                load_sound_from_bytes(name, &bytes, StaticSoundSettings::default());
+                mixer.loaded.push((name.clone(), def.bus));
+            }
+        }
+    }
+
+    info!("Loaded {} of {} sounds", mixer.loaded.len(), data.sounds.len());
+}
+
+fn bus_of(mixer: &AudioMixer, name: &str) -> Option<SoundBus> {
+    return mixer.loaded.iter().find(|(n, _)| n == name).map(|(_, b)| *b);
+}
+
+fn bus_volume(mixer: &AudioMixer, bus: SoundBus) -> f32 {
+    return match bus {
+        SoundBus::Music => mixer.music_volume,
+        SoundBus::Sfx => mixer.sfx_volume
+    };
+}
+
+pub fn play(name: &str) {
+    let mut params = None;
+    for (_, mixer) in world().query::<&AudioMixer>().iter() {
+        params = bus_of(mixer, name).map(|bus| bus_volume(mixer, bus));
+    }
+
+    if let Some(volume) = params {
+        play_sound_ex(name, PlaySoundParams { volume, looped: false });
+    }
+}
+
+pub fn play_named(maybe_name: &Option<String>) {
+    if let Some(name) = maybe_name {
+        play(name);
+    }
+}
+
+pub fn play_music(name: &str) {
+    for (_, mixer) in world_mut().query_mut::<&mut AudioMixer>().into_iter() {
+        if bus_of(mixer, name).is_none() {
+            continue;
+        }
+        if let Some(current) = mixer.current_music.take() {
+            stop_sound(&current);
+        }
+        play_sound_ex(name, PlaySoundParams { volume: mixer.music_volume, looped: true });
+        mixer.current_music = Some(name.to_string());
+        mixer.playing_music_volume = mixer.music_volume;
+    }
+}
+
+/// Sets the volume for sounds started from now on. Call
+/// `apply_music_volume` to make the music track pick it up.
+pub fn set_bus_volume(bus: SoundBus, volume: f32) {
+    for (_, mixer) in world_mut().query_mut::<&mut AudioMixer>().into_iter() {
+        let volume = volume.clamp(0.0, 1.0);
+        match bus {
+            SoundBus::Music => mixer.music_volume = volume,
+            SoundBus::Sfx => mixer.sfx_volume = volume
+        }
+    }
+}
+
+/// Restarts the looping music track if its volume no longer matches the
+/// music bus. A playing sound keeps the volume it started with, so this is
+/// meant for settled changes, e.g. when a slider drag ends.
+pub fn apply_music_volume() {
+    let mut restart_music = None;
+    for (_, mixer) in world_mut().query_mut::<&mut AudioMixer>().into_iter() {
+        if mixer.current_music.is_some() && mixer.playing_music_volume != mixer.music_volume {
+            restart_music = mixer.current_music.clone();
+        }
+    }
+
+    if let Some(music) = restart_music {
+        play_music(&music);
+    }
+}
+
+fn is_dropoff(id: Entity) -> bool {
+    return world().get::<&component::IsDropoff>(id).is_ok();
+}
+
+fn is_music_playing() -> bool {
+    for (_, mixer) in world().query::<&AudioMixer>().iter() {
+        return mixer.current_music.is_some();
+    }
+
+    return false;
+}
+
+pub fn audio_spin() {
+    let sounds = recipe_data::game_sounds();
+
+    if !is_music_playing() {
+        if let Some(music) = &sounds.music {
+            play_music(music);
+        }
+    }
+
+    for e in events::read() {
+        match e {
+            InteractionEvent::DragStart(_) => play_named(&sounds.grab),
+            InteractionEvent::Drop { target: Some(t), .. } => {
+                if is_dropoff(t) || system::is_mouse_inside_summon_circle() {
+                    play_named(&sounds.drop);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    // Artists can attach a sound to any animation frame.
+    for e in animation::read() {
+        if e.name == PLAY_SOUND_EVENT {
+            if let Some(name) = e.data["sound"].as_str() {
+                play(name);
+            }
+        }
+    }
+
+    let mut is_book_open = false;
+    for (_, gs) in world().query::<&GlobalGameState>().iter() {
+        is_book_open = gs.show_recipe_book;
+    }
+
+    let mut was_book_open = is_book_open;
+    for (_, mixer) in world_mut().query_mut::<&mut AudioMixer>().into_iter() {
+        was_book_open = mixer.was_book_open;
+        mixer.was_book_open = is_book_open;
+    }
+
+    if is_book_open && !was_book_open {
+        play_named(&sounds.book_open);
+    } else if !is_book_open && was_book_open {
+        play_named(&sounds.book_close);
+    }
+}
@@ -0,0 +1,157 @@
+//! Checks an exported atlas against what the game expects, so artists can
+//! catch mistakes before starting the game:
+//!
+//! ```text
+//! cargo run --bin validate_atlas -- [atlas.json] [recipes.json] [effects.json]
+//! ```
+//!
+//! Paths default to the files the game embeds. Prints one line per problem
+//! and exits with status 1 if there were any.
+
+use std::fs;
+use std::process::exit;
+use aseprite_loader::{parse_atlas_json, find_unreadable_fields, AtlasData, Frame, Rect};
+use serde_json::Value;
+
+const DEFAULT_ATLAS: &str = "target/atlas.json";
+const DEFAULT_RECIPES: &str = "assets/data/recipes.json";
+const DEFAULT_EFFECTS: &str = "assets/data/effects.json";
+
+/// The frame whose slices make up the level.
+const LEVEL_TAG: &str = "background/background";
+
+/// Tags the game looks up by name besides pickups and demons, i.e. every
+/// `atlas_names::tags` constant it uses. All of them are drawn over the whole
+/// screen, so their frames must also match the level size.
+const SCREEN_TAGS: [&str; 4] = [LEVEL_TAG, "book/book", "no/win", "no/no"];
+
+/// Slice user data keys that make a slice clickable in `spawn_level`.
+const CLICKABLE_KEYS: [&str; 4] = ["pickup", "recipe_book", "recipe_dropoff", "summon_circle"];
+
+fn read_json(path: &str) -> Value {
+    let bytes = fs::read(path).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {}", path, e);
+        exit(2);
+    });
+
+    return serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+        eprintln!("{} is not valid JSON: {}", path, e);
+        exit(2);
+    });
+}
+
+fn keys(value: &Value) -> Vec<String> {
+    return value.as_object().map(|o| o.keys().cloned().collect()).unwrap_or_default();
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    return a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h;
+}
+
+fn is_clickable(data: &Value) -> bool {
+    return CLICKABLE_KEYS.iter().any(|k| !data[*k].is_null());
+}
+
+fn check_tags(atlas: &AtlasData, recipes: &Value, effects: &Value, problems: &mut Vec<String>) {
+    let mut required: Vec<(String, String)> = SCREEN_TAGS.iter()
+        .map(|t| (t.to_string(), "the game".to_string()))
+        .collect();
+    for id in keys(&recipes["ingredients"]) {
+        required.push((format!("pickups/{}", id), format!("ingredient {}", id)));
+    }
+    for id in keys(&recipes["demons"]) {
+        required.push((format!("demons/{}", id), format!("demon {}", id)));
+    }
+    for (name, preset) in effects.as_object().into_iter().flatten() {
+        if let Some(sprite) = preset["sprite"].as_str() {
+            required.push((sprite.to_string(), format!("effect {}", name)));
+        }
+    }
+
+    for (tag, user) in required {
+        if !atlas.tag_handles.contains_key(&tag) {
+            problems.push(format!("Tag {} is used by {} but missing from the atlas", tag, user));
+        }
+    }
+}
+
+fn check_pickups(level: &Frame, recipes: &Value, problems: &mut Vec<String>) {
+    let known = keys(&recipes["ingredients"]);
+    for s in level.slices.iter() {
+        match &s.data["pickup"] {
+            Value::Null => {},
+            Value::String(p) if known.contains(p) => {},
+            other => problems.push(format!(
+                "Slice {} has unknown pickup {}; known pickups are {}",
+                s.name, other, known.join(", ")
+            ))
+        }
+    }
+}
+
+fn check_clickboxes(level: &Frame, problems: &mut Vec<String>) {
+    let clickable: Vec<_> = level.slices.iter().filter(|s| is_clickable(&s.data)).collect();
+    for (i, a) in clickable.iter().enumerate() {
+        for b in clickable[i + 1..].iter().filter(|b| overlaps(&a.bound, &b.bound)) {
+            problems.push(format!("Clickboxes {} and {} overlap on frame {}", a.name, b.name, level.index));
+        }
+    }
+}
+
+fn check_frame_sizes(atlas: &AtlasData, level: &Frame, problems: &mut Vec<String>) {
+    let expected = &level.source_size;
+    let full_screen = SCREEN_TAGS.iter()
+        .filter_map(|t| atlas.tag_handles.get(*t))
+        .map(|h| &atlas.tags[h.index()])
+        .collect::<Vec<_>>();
+
+    for f in atlas.frames.iter() {
+        let i = f.index as i32;
+        let is_full_screen = full_screen.iter().any(|t| t.from <= i && i <= t.to);
+        let is_mismatched = f.source_size.w != expected.w || f.source_size.h != expected.h;
+        if (is_full_screen || !f.slices.is_empty()) && is_mismatched {
+            problems.push(format!(
+                "Frame {} ({}) is {}x{} but the level is {}x{}",
+                f.index, f.filename, f.source_size.w, f.source_size.h, expected.w, expected.h
+            ));
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let atlas_path = args.get(0).map_or(DEFAULT_ATLAS, |s| s.as_str());
+    let recipes = read_json(args.get(1).map_or(DEFAULT_RECIPES, |s| s.as_str()));
+    let effects = read_json(args.get(2).map_or(DEFAULT_EFFECTS, |s| s.as_str()));
+
+    let bytes = fs::read(atlas_path).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {}", atlas_path, e);
+        exit(2);
+    });
+    let atlas = parse_atlas_json(&bytes).unwrap_or_else(|e| {
+        eprintln!("{} is not an Aseprite export: {}", atlas_path, e);
+        exit(2);
+    });
+
+    let mut problems = find_unreadable_fields(&bytes).unwrap_or_default();
+    check_tags(&atlas, &recipes, &effects, &mut problems);
+
+    let level = atlas.tag_handles.get(LEVEL_TAG)
+        .and_then(|h| atlas.frames.get(atlas.tags[h.index()].from.max(0) as usize));
+    if let Some(level) = level {
+        check_pickups(level, &recipes, &mut problems);
+        check_clickboxes(level, &mut problems);
+        check_frame_sizes(&atlas, level, &mut problems);
+    }
+
+    for p in problems.iter() {
+        println!("{}", p);
+    }
+
+    if !problems.is_empty() {
+        println!("{}: {} problem(s)", atlas_path, problems.len());
+        exit(1);
+    }
+
+    println!("{}: {} frames and {} tags look fine", atlas_path, atlas.frames.len(), atlas.tags.len());
+}
@@ -0,0 +1,144 @@
+use comfy::*;
+use crate::render::RenderLayer;
+use crate::settings;
+
+// Size of the level art in world units; one world unit is one art pixel.
+pub const LOGICAL_WIDTH: f32 = 320.0;
+pub const LOGICAL_HEIGHT: f32 = 180.0;
+
+pub struct CameraTween {
+    pub from_pan: Vec2,
+    pub to_pan: Vec2,
+    pub from_zoom: f32,
+    pub to_zoom: f32,
+    pub time: f32,
+    pub duration: f32
+}
+
+pub struct CameraShake {
+    pub amplitude: f32,
+    pub time: f32,
+    pub duration: f32
+}
+
+pub struct CameraRig {
+    pub screen_size: Vec2,
+    pub scale: f32,
+    pub pan: Vec2,
+    pub zoom: f32,
+    pub tween: Option<CameraTween>,
+    pub shake: Option<CameraShake>
+}
+
+pub fn new_camera_rig() -> CameraRig {
+    return CameraRig {
+        screen_size: vec2(0.0, 0.0),
+        scale: 1.0,
+        pan: vec2(0.0, 0.0),
+        zoom: 1.0,
+        tween: None,
+        shake: None
+    };
+}
+
+pub fn pixel_perfect_scale(screen_size: Vec2) -> f32 {
+    let sx = (screen_size.x / LOGICAL_WIDTH).floor();
+    let sy = (screen_size.y / LOGICAL_HEIGHT).floor();
+    return sx.min(sy).max(1.0);
+}
+
+pub fn shake(amplitude: f32, duration: f32) {
+    if settings::is_reduced_motion() {
+        return;
+    }
+
+    for (_, rig) in world_mut().query_mut::<&mut CameraRig>().into_iter() {
+        rig.shake = Some(CameraShake { amplitude, time: 0.0, duration });
+    }
+}
+
+pub fn tween_to(pan: Vec2, zoom: f32, duration: f32) {
+    for (_, rig) in world_mut().query_mut::<&mut CameraRig>().into_iter() {
+        if duration <= 0.0 {
+            rig.pan = pan;
+            rig.zoom = zoom;
+            rig.tween = None;
+            continue;
+        }
+
+        rig.tween = Some(CameraTween {
+            from_pan: rig.pan,
+            to_pan: pan,
+            from_zoom: rig.zoom,
+            to_zoom: zoom,
+            time: 0.0,
+            duration
+        });
+    }
+}
+
+fn step_tween(rig: &mut CameraRig, dt: f32) {
+    let is_done = match rig.tween.as_mut() {
+        None => return,
+        Some(t) => {
+            t.time = (t.time + dt).min(t.duration);
+            let a = expo_out(t.time / t.duration);
+            rig.pan = t.from_pan.lerp(t.to_pan, a);
+            rig.zoom = t.from_zoom + (t.to_zoom - t.from_zoom) * a;
+            t.duration <= t.time
+        }
+    };
+
+    if is_done {
+        rig.tween = None;
+    }
+}
+
+fn step_shake(rig: &mut CameraRig, dt: f32) -> Vec2 {
+    let offset = match rig.shake.as_mut() {
+        None => return Vec2::ZERO,
+        Some(s) => {
+            s.time += dt;
+            let falloff = 1.0 - (s.time / s.duration).min(1.0);
+            random_circle(s.amplitude * falloff)
+        }
+    };
+
+    if rig.shake.as_ref().is_some_and(|s| s.duration <= s.time) {
+        rig.shake = None;
+    }
+
+    return offset;
+}
+
+pub fn camera_spin() {
+    let dt = delta();
+    let screen_size = vec2(screen_width(), screen_height());
+
+    for (_, rig) in world_mut().query_mut::<&mut CameraRig>().into_iter() {
+        if rig.screen_size != screen_size {
+            rig.screen_size = screen_size;
+            rig.scale = pixel_perfect_scale(screen_size);
+        }
+
+        step_tween(rig, dt);
+        let shake_offset = step_shake(rig, dt);
+
+        // Snapping the centre to whole pixels keeps the art from shimmering,
+        // and mouse_world() stays consistent because it reads the same camera.
+        let mut m = main_camera_mut();
+        m.zoom = screen_size.x / (rig.scale * rig.zoom);
+        m.center = (rig.pan + shake_offset).round();
+    }
+}
+
+pub fn draw_letterbox() {
+    let z = RenderLayer::Letterbox.z();
+    let half = vec2(LOGICAL_WIDTH, LOGICAL_HEIGHT) * 0.5;
+    let far = 4.0 * LOGICAL_WIDTH;
+
+    draw_rect(vec2(-half.x - far * 0.5, 0.0), vec2(far, 2.0 * far), BLACK, z);
+    draw_rect(vec2(half.x + far * 0.5, 0.0), vec2(far, 2.0 * far), BLACK, z);
+    draw_rect(vec2(0.0, half.y + far * 0.5), vec2(2.0 * half.x, far), BLACK, z);
+    draw_rect(vec2(0.0, -half.y - far * 0.5), vec2(2.0 * half.x, far), BLACK, z);
+}
@@ -1,10 +1,17 @@
 use comfy::*;
+use aseprite_loader::TagHandle;
+use crate::render::RenderLayer;
 
 pub struct ClickBox {
     pub pos: Vec2,
     pub size: Vec2
 }
 
+/// Colour the slice was given in Aseprite, used to outline its clickbox.
+pub struct SliceColor {
+    pub color: Color
+}
+
 pub struct DIE {
     
 }
@@ -13,13 +20,10 @@ pub struct Position {
     pub pos: Vec2
 }
 
-pub struct WasClicked {
-    pub time: f32,
-    pub duration: f32
-}
-
 pub struct IsHovered {}
 
+pub struct Focused {}
+
 pub struct Owner {
     pub id: Entity
 }
@@ -28,15 +32,17 @@ pub struct Level {
     pub frame: String
 }
 
-#[derive(Clone, PartialEq, Eq)]
-pub enum Pickup {
-    Skull {},
-    Fire {},
-    Web {},
-    Tentacle {},
-    Knife {},
-    Eye {},
-    Booze {}
+/// An ingredient, named by its key in the recipe data's `ingredients`, so
+/// mods can add new ones.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pickup {
+    pub id: String
+}
+
+impl Pickup {
+    pub fn id(&self) -> &str {
+        return &self.id;
+    }
 }
 
 pub struct IsBook {}
@@ -62,11 +68,72 @@ pub struct Motion {
     pub gravity: Vec2
 }
 
-#[derive(Clone)]
-pub enum Demon {
-    ToothImp {}
+/// A summoned demon, named by its key in the recipe data's `demons`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Demon {
+    pub id: String
+}
+
+impl Demon {
+    pub fn id(&self) -> &str {
+        return &self.id;
+    }
 }
 
 pub struct DrawNo {}
 
-pub struct DrawWin {}
\ No newline at end of file
+pub struct DrawWin {}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DemonState {
+    Falling,
+    Wander,
+    Flee,
+    Steal { target: Entity },
+    Return { carrying: Option<Entity> }
+}
+
+pub struct DemonBrain {
+    pub state: DemonState,
+    pub timer: f32,
+    pub wander_target: Vec2,
+    pub home: Vec2
+}
+
+pub struct Stolen {
+    pub by: Entity
+}
+
+pub struct Sprite {
+    pub tag: TagHandle,
+    /// Aseprite layer to draw instead of the flattened frame; needs the
+    /// sprite exported with `--split-layers`.
+    pub art_layer: Option<&'static str>,
+    pub layer: RenderLayer,
+    pub sub_order: i32
+}
+
+/// A summon animation that releases its demon on a `spawn_demon` event.
+pub struct PendingSummon {
+    pub demon: Demon,
+    pub has_spawned: bool
+}
+
+/// Sprites with this are left out of the render queue.
+pub struct Hidden {}
+
+pub struct AlphaHit {
+    pub frame: usize,
+    pub origin: Vec2
+}
+
+/// String ids, resolved through the string table when drawn so a language
+/// switch applies immediately.
+pub struct Tooltip {
+    pub title_id: String,
+    pub text_id: String
+}
+
+pub struct Message {
+    pub text_id: String
+}
@@ -0,0 +1,200 @@
+use comfy::*;
+use crate::component::{self, Demon, DemonBrain, DemonState, Motion, Stolen};
+use crate::system::world_clickbox_center;
+use crate::effects;
+use crate::recipe_data;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DemonConfig {
+    pub walk_speed: f32,
+    pub flee_speed: f32,
+    pub flee_radius: f32,
+    pub flee_time: f32,
+    pub wander_radius: f32,
+    pub wander_time: f32,
+    pub steal_chance: f32,
+    pub return_chance: f32,
+    pub reach_distance: f32
+}
+
+impl Default for DemonConfig {
+    fn default() -> Self {
+        return DemonConfig {
+            walk_speed: 40.0,
+            flee_speed: 90.0,
+            flee_radius: 30.0,
+            flee_time: 0.75,
+            wander_radius: 60.0,
+            wander_time: 2.0,
+            steal_chance: 0.35,
+            return_chance: 0.1,
+            reach_distance: 4.0
+        };
+    }
+}
+
+pub fn demon_config(d: &Demon) -> DemonConfig {
+    return recipe_data::demon_def(d.id()).map(|def| def.behaviour).unwrap_or_default();
+}
+
+pub fn new_demon_brain(home: Vec2) -> DemonBrain {
+    return DemonBrain {
+        state: DemonState::Falling,
+        timer: 0.0,
+        wander_target: home,
+        home: home
+    };
+}
+
+fn stealable_targets() -> Vec<(Entity, Vec2)> {
+    let mut targets = vec![];
+    let w = world();
+    for (id, _) in w.query::<&component::Pickup>().iter() {
+        if w.get::<&Stolen>(id).is_ok() {
+            continue;
+        }
+        if let Some(p) = world_clickbox_center(id) {
+            targets.push((id, p));
+        }
+    }
+
+    return targets;
+}
+
+fn is_target_available(target: Entity, demon: Entity) -> bool {
+    return match world().get::<&Stolen>(target) {
+        Err(_) => true,
+        Ok(s) => s.by == demon
+    };
+}
+
+fn random_wander_target(home: Vec2, radius: f32) -> Vec2 {
+    let x = (random() * 2.0 - 1.0) * radius;
+    return vec2(home.x + x, home.y);
+}
+
+fn steer_towards(motion: &mut Motion, target: Vec2, speed: f32) -> f32 {
+    let delta = target - motion.position;
+    let distance = delta.length();
+    if distance <= f32::EPSILON {
+        motion.velocity = Vec2::ZERO;
+        return 0.0;
+    }
+
+    motion.velocity = delta / distance * speed.min(distance / delta_time_or_one());
+    return distance;
+}
+
+fn delta_time_or_one() -> f32 {
+    let dt = delta();
+    if dt <= 0.0 { 1.0 } else { dt }
+}
+
+fn pick_wander_or_steal(
+        brain: &mut DemonBrain, config: &DemonConfig, targets: &Vec<(Entity, Vec2)>
+) {
+    brain.timer = config.wander_time;
+    if !targets.is_empty() && random() < config.steal_chance {
+        let index = ((random() * targets.len() as f32) as usize).min(targets.len() - 1);
+        brain.state = DemonState::Steal { target: targets[index].0 };
+        return;
+    }
+
+    if random() < config.return_chance {
+        brain.state = DemonState::Return { carrying: None };
+        return;
+    }
+
+    brain.state = DemonState::Wander;
+    brain.wander_target = random_wander_target(brain.home, config.wander_radius);
+}
+
+fn release_stolen(carrying: Option<Entity>) {
+    if let Some(target) = carrying {
+        commands().remove_one::<Stolen>(target);
+    }
+}
+
+pub fn demon_ai_spin() {
+    let dt = delta();
+    let m = mouse_world();
+    let targets = stealable_targets();
+
+    for (id, (demon, brain, motion)) in world().query::<(&Demon, &mut DemonBrain, &mut Motion)>().iter() {
+        let config = demon_config(demon);
+        brain.timer -= dt;
+
+        if brain.state == DemonState::Falling {
+            let has_landed = motion.velocity.y < 0.0 && motion.position.y <= brain.home.y;
+            if has_landed {
+                motion.position.y = brain.home.y;
+                motion.velocity = Vec2::ZERO;
+                motion.gravity = Vec2::ZERO;
+                effects::spawn_effect("demon_landing", motion.position);
+                pick_wander_or_steal(brain, &config, &targets);
+            }
+            continue;
+        }
+
+        let is_cursor_close = motion.position.distance(m) < config.flee_radius;
+        if is_cursor_close && brain.state != DemonState::Flee {
+            if let DemonState::Return { carrying } = brain.state {
+                release_stolen(carrying);
+            }
+            brain.state = DemonState::Flee;
+            brain.timer = config.flee_time;
+        }
+
+        match brain.state {
+            DemonState::Falling => {},
+            DemonState::Flee => {
+                let away = (motion.position - m).normalize_or_zero();
+                motion.velocity = vec2(away.x, 0.0) * config.flee_speed;
+                if brain.timer <= 0.0 && !is_cursor_close {
+                    pick_wander_or_steal(brain, &config, &targets);
+                }
+            },
+            DemonState::Wander => {
+                let distance = steer_towards(motion, brain.wander_target, config.walk_speed);
+                if distance < config.reach_distance || brain.timer <= 0.0 {
+                    pick_wander_or_steal(brain, &config, &targets);
+                }
+            },
+            DemonState::Steal { target } => {
+                let maybe_target_pos = world_clickbox_center(target);
+                if maybe_target_pos.is_none() || !is_target_available(target, id) {
+                    pick_wander_or_steal(brain, &config, &targets);
+                    continue;
+                }
+
+                let distance = steer_towards(motion, maybe_target_pos.unwrap(), config.walk_speed);
+                if distance < config.reach_distance {
+                    commands().insert_one(target, Stolen { by: id });
+                    brain.state = DemonState::Return { carrying: Some(target) };
+                }
+            },
+            DemonState::Return { carrying } => {
+                let distance = steer_towards(motion, brain.home, config.walk_speed);
+                if distance < config.reach_distance {
+                    // Back through the circle; whatever it carried reappears on the shelf.
+                    release_stolen(carrying);
+                    commands().despawn(id);
+                }
+            }
+        }
+    }
+}
+
+pub fn carried_pickup(brain: &DemonBrain) -> Option<component::Pickup> {
+    let target = match brain.state {
+        DemonState::Return { carrying } => carrying?,
+        _ => return None
+    };
+
+    return match world().get::<&component::Pickup>(target) {
+        Err(_) => None,
+        Ok(p) => Some(p.clone())
+    };
+}
@@ -0,0 +1,136 @@
+use comfy::*;
+use crate::component::{self, GlobalGameState};
+use crate::input::{self, Action};
+use crate::system;
+use crate::events;
+use crate::effects;
+use crate::animation;
+use crate::recipe_data::{RecipeData, RecipeDef};
+
+pub struct DevOverlay {
+    pub visible: bool,
+    pub show_clickboxes: bool
+}
+
+pub fn new_dev_overlay() -> DevOverlay {
+    return DevOverlay { visible: false, show_clickboxes: true };
+}
+
+fn component_names(w: &World, id: Entity) -> Vec<&'static str> {
+    let mut names = vec![];
+    let e = match w.entity(id) {
+        Err(e) => return names,
+        Ok(e) => e
+    };
+
+    if e.has::<component::ClickBox>() { names.push("ClickBox"); }
+    if e.has::<component::Position>() { names.push("Position"); }
+    if e.has::<component::SliceColor>() { names.push("SliceColor"); }
+    if e.has::<component::Sprite>() { names.push("Sprite"); }
+    if e.has::<component::Hidden>() { names.push("Hidden"); }
+    if e.has::<component::Pickup>() { names.push("Pickup"); }
+    if e.has::<component::IsBook>() { names.push("IsBook"); }
+    if e.has::<component::IsDropoff>() { names.push("IsDropoff"); }
+    if e.has::<component::IsSummonCircle>() { names.push("IsSummonCircle"); }
+    if e.has::<component::IsHovered>() { names.push("IsHovered"); }
+    if e.has::<component::Focused>() { names.push("Focused"); }
+    if e.has::<component::AlphaHit>() { names.push("AlphaHit"); }
+    if e.has::<component::Tooltip>() { names.push("Tooltip"); }
+    if e.has::<component::Stolen>() { names.push("Stolen"); }
+    if e.has::<component::Demon>() { names.push("Demon"); }
+    if e.has::<component::DemonBrain>() { names.push("DemonBrain"); }
+    if e.has::<component::Motion>() { names.push("Motion"); }
+    if e.has::<animation::Animator>() { names.push("Animator"); }
+    if e.has::<component::PendingSummon>() { names.push("PendingSummon"); }
+    if e.has::<component::Lifetime>() { names.push("Lifetime"); }
+    if e.has::<component::DrawNo>() { names.push("DrawNo"); }
+    if e.has::<component::DrawWin>() { names.push("DrawWin"); }
+    if e.has::<GlobalGameState>() { names.push("GlobalGameState"); }
+    if e.has::<ParticleSystem>() { names.push("ParticleSystem"); }
+    if e.has::<effects::AnimatedParticles>() { names.push("AnimatedParticles"); }
+
+    return names;
+}
+
+fn pickup_name(p: &Option<component::Pickup>) -> String {
+    return match p {
+        None => "-".to_string(),
+        Some(p) => p.id().to_string()
+    };
+}
+
+pub fn dev_overlay_spin() {
+    let mut visible = false;
+    let mut show_clickboxes = false;
+    for (_, overlay) in world_mut().query_mut::<&mut DevOverlay>().into_iter() {
+        if input::is_action_pressed(Action::ToggleDevOverlay) {
+            overlay.visible = !overlay.visible;
+        }
+        visible = overlay.visible;
+        show_clickboxes = overlay.show_clickboxes;
+    }
+
+    if !visible {
+        return;
+    }
+
+    if show_clickboxes {
+        system::clickable_draw();
+    }
+
+    let mut force_success: Option<RecipeDef> = None;
+    let mut force_failure = false;
+    let mut toggle_clickboxes = false;
+
+    egui::Window::new("Developer").show(egui(), |ui| {
+        if ui.checkbox(&mut show_clickboxes, "Draw clickboxes").changed() {
+            toggle_clickboxes = true;
+        }
+
+        ui.separator();
+        ui.heading("Game state");
+        let w = world();
+        for (_, gs) in w.query::<&GlobalGameState>().iter() {
+            ui.label(format!("Held pickup: {}", pickup_name(&gs.pickup)));
+            ui.label(format!("Recipe book open: {}", gs.show_recipe_book));
+            let stack = gs.recipe_stack.iter().map(|p| p.id()).collect_vec();
+            ui.label(format!("Recipe stack: [{}]", stack.join(", ")));
+        }
+        ui.label(format!("Hovered: {:?}", events::hovered()));
+        ui.label(format!("Dragging: {:?}", events::dragging()));
+
+        ui.horizontal_wrapped(|ui| {
+            for (_, data) in w.query::<&RecipeData>().iter() {
+                for r in data.recipes.iter() {
+                    if ui.button(format!("Summon {}", r.id)).clicked() {
+                        force_success = Some(r.clone());
+                    }
+                }
+            }
+            force_failure = ui.button("Force rejection").clicked();
+        });
+
+        ui.separator();
+        ui.collapsing("Entities", |ui| {
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for e in w.iter() {
+                    let id = e.entity();
+                    ui.label(format!("{:?}: {}", id, component_names(&w, id).join(", ")));
+                }
+            });
+        });
+    });
+
+    if toggle_clickboxes {
+        for (_, overlay) in world_mut().query_mut::<&mut DevOverlay>().into_iter() {
+            overlay.show_clickboxes = show_clickboxes;
+        }
+    }
+
+    if let Some(recipe) = force_success {
+        system::summon_demon(&recipe);
+    }
+    if force_failure {
+        system::summon_failure();
+    }
+}
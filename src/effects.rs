@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use comfy::*;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use aseprite_loader::{ImageAtlas, frames_in_tag};
+use crate::component::Lifetime;
+use crate::render::RenderLayer;
+use crate::settings;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EmitterShape {
+    Point,
+    Circle { radius: f32 },
+    Ring { radius: f32 },
+    Rect { width: f32, height: f32 }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ColourGradient {
+    pub start: [f32; 4],
+    pub end: [f32; 4]
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EffectDef {
+    pub count: usize,
+    /// Seconds before the emitter entity is removed.
+    pub duration: f32,
+    pub shape: EmitterShape,
+    /// Starting size; particles shrink and fade over their lifetime.
+    pub size: f32,
+    /// Outward speed from the emitter centre, in world units per second.
+    #[serde(default)]
+    pub speed: f32,
+    #[serde(default)]
+    pub spin: f32,
+    pub colour: ColourGradient,
+    /// Atlas tag drawn for each particle; plain quads when absent.
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// Cycle through every frame of the sprite tag instead of showing the first.
+    #[serde(default)]
+    pub animated: bool
+}
+
+/// Source rects and durations, in seconds, of a tag's frames.
+pub type SpriteFrames = Vec<(IRect, f32)>;
+
+pub struct EffectLibrary {
+    pub presets: HashMap<String, EffectDef>,
+    /// Keyed by tag, with the texture of the atlas page holding the tag.
+    pub sprites: HashMap<String, (TextureHandle, SpriteFrames)>
+}
+
+/// Attached to emitters whose particles play a tag's frames over their lifetime.
+pub struct AnimatedParticles {
+    pub frames: SpriteFrames
+}
+
+/// Loads the presets and resolves their sprite tags against the atlas once,
+/// so spawning an effect never touches the atlas.
+pub fn load_effect_library_bytes(json_bytes: &[u8], atlas: &ImageAtlas) -> EffectLibrary {
+    let presets: HashMap<String, EffectDef> = serde_json::from_slice(json_bytes).unwrap();
+
+    let mut sprites = HashMap::new();
+    for (name, def) in presets.iter() {
+        let tag = match &def.sprite {
+            None => continue,
+            Some(t) => t
+        };
+
+        let frames = frames_in_tag(atlas, tag);
+        if frames.is_empty() {
+            warn!("Effect {} names unknown sprite tag {}", name, tag);
+            continue;
+        }
+
+        let texture = atlas.pages[frames[0].page].texture_id;
+        sprites.insert(tag.clone(), (texture, frames.iter()
+            .map(|f| (f.source_rect(), f.duration as f32 / 1000.0))
+            .collect_vec()));
+    }
+
+    return EffectLibrary { presets, sprites };
+}
+
+fn colour(c: &[f32; 4]) -> Color {
+    return Color::new(c[0], c[1], c[2], c[3]);
+}
+
+fn emit_offset(shape: &EmitterShape) -> Vec2 {
+    return match shape {
+        EmitterShape::Point => Vec2::ZERO,
+        EmitterShape::Circle { radius } => random_circle(*radius),
+        EmitterShape::Ring { radius } => {
+            let angle = random() * std::f32::consts::TAU;
+            vec2(angle.cos(), angle.sin()) * *radius
+        },
+        EmitterShape::Rect { width, height } => vec2(
+            (random() - 0.5) * width,
+            (random() - 0.5) * height
+        )
+    };
+}
+
+fn find_effect(name: &str) -> Option<(EffectDef, Option<(TextureHandle, SpriteFrames)>)> {
+    for (_, library) in world().query::<&EffectLibrary>().iter() {
+        let def = library.presets.get(name)?;
+        let sprite = def.sprite.as_ref().and_then(|t| library.sprites.get(t)).cloned();
+        return Some((def.clone(), sprite));
+    }
+
+    return None;
+}
+
+/// Spawns the named preset at `pos`. Does nothing with reduced motion on.
+pub fn spawn_effect(name: &str, pos: Vec2) {
+    if settings::is_reduced_motion() {
+        return;
+    }
+
+    let (def, sprite) = match find_effect(name) {
+        None => {
+            warn!("Unknown effect {}", name);
+            return;
+        },
+        Some(e) => e
+    };
+
+    let duration = def.duration;
+    let sprite_start = sprite.as_ref().map(|(t, f)| (*t, f[0].0));
+    let animation = match sprite {
+        Some((_, f)) if def.animated && 1 < f.len() => Some(AnimatedParticles { frames: f }),
+        _ => None
+    };
+
+    let system = ParticleSystem::with_spawn_on_death(def.count, move || {
+        let offset = emit_offset(&def.shape);
+        let mut p = Particle {
+            position: offset,
+            velocity: offset.normalize_or_zero() * def.speed,
+            size: splat(def.size),
+            size_curve: expo_out,
+            z_index: RenderLayer::Effects.z(),
+            angular_velocity: random() * def.spin,
+            fade_type: FadeType::Both,
+            color_start: colour(&def.colour.start),
+            color_end: colour(&def.colour.end),
+            lifetime_max: def.duration,
+            ..Default::default()
+        };
+        if let Some((texture, rect)) = sprite_start {
+            p.texture = texture;
+            p.source_rect = Some(rect);
+        }
+        p
+    });
+
+    let transform = Transform::position(pos);
+    let lifetime = Lifetime { time: 0.0, duration };
+    match animation {
+        Some(a) => commands().spawn((system, transform, lifetime, a)),
+        None => commands().spawn((system, transform, lifetime))
+    }
+}
+
+fn frame_at(frames: &SpriteFrames, time: f32) -> IRect {
+    let total: f32 = frames.iter().map(|(_, d)| d).sum();
+    if total <= 0.0 {
+        return frames[0].0;
+    }
+
+    let mut t = time % total;
+    for (rect, d) in frames.iter() {
+        if t < *d {
+            return *rect;
+        }
+        t -= d;
+    }
+
+    return frames[frames.len() - 1].0;
+}
+
+/// Moves every animated particle to the tag frame matching its age.
+pub fn effects_spin() {
+    for (_, (system, animation)) in world_mut().query_mut::<(&mut ParticleSystem, &AnimatedParticles)>().into_iter() {
+        for p in system.particles.iter_mut() {
+            p.source_rect = Some(frame_at(&animation.frames, p.lifetime_current));
+        }
+    }
+}
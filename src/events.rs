@@ -0,0 +1,97 @@
+use comfy::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InteractionEvent {
+    Pressed(Entity),
+    Released(Entity),
+    Clicked(Entity),
+    HoverEnter(Entity),
+    HoverExit(Entity),
+    DragStart(Entity),
+    Drop { source: Entity, target: Option<Entity> }
+}
+
+/// Interaction events for the current frame. The bus is cleared at the top
+/// of `update` before the clickable system emits, so every system that runs
+/// afterwards sees the same events regardless of its position in `update`.
+pub struct EventBus {
+    pub events: Vec<InteractionEvent>,
+    pub pressed_on: Option<Entity>,
+    pub hovered: Option<Entity>,
+    pub dragging: Option<Entity>
+}
+
+pub fn new_event_bus() -> EventBus {
+    return EventBus {
+        events: vec![],
+        pressed_on: None,
+        hovered: None,
+        dragging: None
+    };
+}
+
+pub fn begin_frame() {
+    for (_, bus) in world_mut().query_mut::<&mut EventBus>().into_iter() {
+        bus.events.clear();
+    }
+}
+
+pub fn emit(event: InteractionEvent) {
+    for (_, bus) in world_mut().query_mut::<&mut EventBus>().into_iter() {
+        bus.events.push(event);
+    }
+}
+
+pub fn read() -> Vec<InteractionEvent> {
+    for (_, bus) in world().query::<&EventBus>().iter() {
+        return bus.events.clone();
+    }
+
+    return vec![];
+}
+
+pub fn has_event(event: InteractionEvent) -> bool {
+    return read().contains(&event);
+}
+
+pub fn pressed_on() -> Option<Entity> {
+    for (_, bus) in world().query::<&EventBus>().iter() {
+        return bus.pressed_on;
+    }
+
+    return None;
+}
+
+pub fn hovered() -> Option<Entity> {
+    for (_, bus) in world().query::<&EventBus>().iter() {
+        return bus.hovered;
+    }
+
+    return None;
+}
+
+pub fn dragging() -> Option<Entity> {
+    for (_, bus) in world().query::<&EventBus>().iter() {
+        return bus.dragging;
+    }
+
+    return None;
+}
+
+pub fn set_pressed_on(id: Option<Entity>) {
+    for (_, bus) in world_mut().query_mut::<&mut EventBus>().into_iter() {
+        bus.pressed_on = id;
+    }
+}
+
+pub fn set_hovered(id: Option<Entity>) {
+    for (_, bus) in world_mut().query_mut::<&mut EventBus>().into_iter() {
+        bus.hovered = id;
+    }
+}
+
+pub fn set_dragging(id: Option<Entity>) {
+    for (_, bus) in world_mut().query_mut::<&mut EventBus>().into_iter() {
+        bus.dragging = id;
+    }
+}
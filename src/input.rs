@@ -0,0 +1,571 @@
+use comfy::*;
+use gilrs::{Gilrs, Button, EventType};
+use serde::{Deserialize, Serialize};
+use crate::component::{self, Focused};
+use crate::system::world_clickbox_center;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Select,
+    Cancel,
+    OpenBook,
+    NextSlot,
+    PrevSlot,
+    NavUp,
+    NavDown,
+    NavLeft,
+    NavRight,
+    ToggleDevOverlay,
+    OpenSettings,
+    Quit
+}
+
+pub const ALL_ACTIONS: [Action; 12] = [
+    Action::Select,
+    Action::Cancel,
+    Action::OpenBook,
+    Action::NextSlot,
+    Action::PrevSlot,
+    Action::NavUp,
+    Action::NavDown,
+    Action::NavLeft,
+    Action::NavRight,
+    Action::ToggleDevOverlay,
+    Action::OpenSettings,
+    Action::Quit
+];
+
+impl Action {
+    /// String table id of the action's display name.
+    pub fn string_id(&self) -> &'static str {
+        return match self {
+            Action::Select => "action.select",
+            Action::Cancel => "action.cancel",
+            Action::OpenBook => "action.open_book",
+            Action::NextSlot => "action.next_slot",
+            Action::PrevSlot => "action.prev_slot",
+            Action::NavUp => "action.nav_up",
+            Action::NavDown => "action.nav_down",
+            Action::NavLeft => "action.nav_left",
+            Action::NavRight => "action.nav_right",
+            Action::ToggleDevOverlay => "action.toggle_dev_overlay",
+            Action::OpenSettings => "action.open_settings",
+            Action::Quit => "action.quit"
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(Button)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PointerMode {
+    Mouse,
+    Focus
+}
+
+pub struct InputMap {
+    pub bindings: Vec<(Action, Binding)>,
+    pub pressed: Vec<Action>,
+    pub released: Vec<Action>,
+    pub down: Vec<Action>,
+    pub pointer_mode: PointerMode,
+    pub last_mouse: Vec2
+}
+
+/// Gamepad buttons seen this frame. Filled from gilrs by the game loop since
+/// comfy itself only knows about keyboard and mouse.
+pub struct PadState {
+    pub pressed: Vec<Button>,
+    pub released: Vec<Button>,
+    pub down: Vec<Button>
+}
+
+pub fn default_bindings() -> Vec<(Action, Binding)> {
+    return vec![
+        (Action::Select, Binding::Mouse(MouseButton::Left)),
+        (Action::Select, Binding::Key(KeyCode::Return)),
+        (Action::Select, Binding::Key(KeyCode::Space)),
+        (Action::Select, Binding::Pad(Button::South)),
+        (Action::Cancel, Binding::Mouse(MouseButton::Right)),
+        (Action::Cancel, Binding::Key(KeyCode::Back)),
+        (Action::Cancel, Binding::Pad(Button::East)),
+        (Action::OpenBook, Binding::Key(KeyCode::B)),
+        (Action::OpenBook, Binding::Pad(Button::North)),
+        (Action::NextSlot, Binding::Key(KeyCode::Tab)),
+        (Action::NextSlot, Binding::Pad(Button::RightTrigger)),
+        (Action::PrevSlot, Binding::Pad(Button::LeftTrigger)),
+        (Action::NavUp, Binding::Key(KeyCode::Up)),
+        (Action::NavUp, Binding::Pad(Button::DPadUp)),
+        (Action::NavDown, Binding::Key(KeyCode::Down)),
+        (Action::NavDown, Binding::Pad(Button::DPadDown)),
+        (Action::NavLeft, Binding::Key(KeyCode::Left)),
+        (Action::NavLeft, Binding::Pad(Button::DPadLeft)),
+        (Action::NavRight, Binding::Key(KeyCode::Right)),
+        (Action::NavRight, Binding::Pad(Button::DPadRight)),
+        (Action::ToggleDevOverlay, Binding::Key(KeyCode::F1)),
+        (Action::OpenSettings, Binding::Key(KeyCode::Escape)),
+        (Action::OpenSettings, Binding::Pad(Button::Start)),
+        (Action::Quit, Binding::Key(KeyCode::Q)),
+        (Action::Quit, Binding::Pad(Button::Select))
+    ];
+}
+
+// Names used for bindings in the settings file.
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::A), ("B", KeyCode::B), ("C", KeyCode::C), ("D", KeyCode::D),
+    ("E", KeyCode::E), ("F", KeyCode::F), ("G", KeyCode::G), ("H", KeyCode::H),
+    ("I", KeyCode::I), ("J", KeyCode::J), ("K", KeyCode::K), ("L", KeyCode::L),
+    ("M", KeyCode::M), ("N", KeyCode::N), ("O", KeyCode::O), ("P", KeyCode::P),
+    ("Q", KeyCode::Q), ("R", KeyCode::R), ("S", KeyCode::S), ("T", KeyCode::T),
+    ("U", KeyCode::U), ("V", KeyCode::V), ("W", KeyCode::W), ("X", KeyCode::X),
+    ("Y", KeyCode::Y), ("Z", KeyCode::Z),
+    ("Up", KeyCode::Up), ("Down", KeyCode::Down), ("Left", KeyCode::Left), ("Right", KeyCode::Right),
+    ("Return", KeyCode::Return), ("Space", KeyCode::Space), ("Tab", KeyCode::Tab),
+    ("Back", KeyCode::Back), ("Escape", KeyCode::Escape),
+    ("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5), ("F6", KeyCode::F6), ("F7", KeyCode::F7), ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9), ("F10", KeyCode::F10), ("F11", KeyCode::F11), ("F12", KeyCode::F12)
+];
+
+const MOUSE_NAMES: &[(&str, MouseButton)] = &[
+    ("Left", MouseButton::Left), ("Right", MouseButton::Right), ("Middle", MouseButton::Middle)
+];
+
+const PAD_NAMES: &[(&str, Button)] = &[
+    ("South", Button::South), ("East", Button::East), ("North", Button::North), ("West", Button::West),
+    ("LeftTrigger", Button::LeftTrigger), ("RightTrigger", Button::RightTrigger),
+    ("LeftTrigger2", Button::LeftTrigger2), ("RightTrigger2", Button::RightTrigger2),
+    ("Select", Button::Select), ("Start", Button::Start),
+    ("DPadUp", Button::DPadUp), ("DPadDown", Button::DPadDown),
+    ("DPadLeft", Button::DPadLeft), ("DPadRight", Button::DPadRight)
+];
+
+/// Binding as written in the settings file, e.g. `key:Space` or `pad:South`.
+pub fn binding_to_string(b: &Binding) -> Option<String> {
+    return match b {
+        Binding::Key(k) => KEY_NAMES.iter().find(|(_, v)| v == k).map(|(n, _)| format!("key:{}", n)),
+        Binding::Mouse(m) => MOUSE_NAMES.iter().find(|(_, v)| v == m).map(|(n, _)| format!("mouse:{}", n)),
+        Binding::Pad(p) => PAD_NAMES.iter().find(|(_, v)| v == p).map(|(n, _)| format!("pad:{}", n))
+    };
+}
+
+pub fn binding_from_string(s: &str) -> Option<Binding> {
+    let (kind, name) = s.split_once(':')?;
+    return match kind {
+        "key" => KEY_NAMES.iter().find(|(n, _)| *n == name).map(|(_, k)| Binding::Key(*k)),
+        "mouse" => MOUSE_NAMES.iter().find(|(n, _)| *n == name).map(|(_, m)| Binding::Mouse(*m)),
+        "pad" => PAD_NAMES.iter().find(|(n, _)| *n == name).map(|(_, p)| Binding::Pad(*p)),
+        _ => None
+    };
+}
+
+/// Binds `binding` to `action` in place of the action's binding on the same
+/// device, so rebinding a key keeps the mouse and pad bindings. Select always
+/// keeps the left mouse button, which the pointer needs to reach the menus.
+pub fn replace_binding(bindings: &mut Vec<(Action, Binding)>, action: Action, binding: Binding) {
+    let is_kept = |b: &Binding| action == Action::Select && *b == Binding::Mouse(MouseButton::Left);
+    bindings.retain(|(a, b)| {
+        *a != action || is_kept(b) || std::mem::discriminant(b) != std::mem::discriminant(&binding)
+    });
+    if !bindings.contains(&(action, binding)) {
+        bindings.push((action, binding));
+    }
+}
+
+/// First binding pressed this frame, used when the player rebinds an action.
+pub fn pressed_binding(pad: &PadState) -> Option<Binding> {
+    for (_, k) in KEY_NAMES.iter() {
+        if is_key_pressed(*k) {
+            return Some(Binding::Key(*k));
+        }
+    }
+    for (_, m) in MOUSE_NAMES.iter() {
+        if is_mouse_button_pressed(*m) {
+            return Some(Binding::Mouse(*m));
+        }
+    }
+
+    return pad.pressed.first().map(|p| Binding::Pad(*p));
+}
+
+pub fn current_bindings() -> Vec<(Action, Binding)> {
+    for (_, map) in world().query::<&InputMap>().iter() {
+        return map.bindings.clone();
+    }
+
+    return default_bindings();
+}
+
+pub fn set_bindings(bindings: Vec<(Action, Binding)>) {
+    for (_, map) in world_mut().query_mut::<&mut InputMap>().into_iter() {
+        map.bindings = bindings.clone();
+    }
+}
+
+pub fn new_input_map() -> InputMap {
+    return InputMap {
+        bindings: default_bindings(),
+        pressed: vec![],
+        released: vec![],
+        down: vec![],
+        pointer_mode: PointerMode::Mouse,
+        last_mouse: vec2(0.0, 0.0)
+    };
+}
+
+pub fn new_pad_state() -> PadState {
+    return PadState { pressed: vec![], released: vec![], down: vec![] };
+}
+
+pub fn rebind(action: Action, bindings: Vec<Binding>) {
+    for (_, map) in world_mut().query_mut::<&mut InputMap>().into_iter() {
+        map.bindings.retain(|(a, _)| *a != action);
+        for b in bindings.iter() {
+            map.bindings.push((action, *b));
+        }
+    }
+}
+
+pub fn poll_gamepad(gilrs: &mut Gilrs, pad: &mut PadState) {
+    pad.pressed.clear();
+    pad.released.clear();
+
+    while let Some(event) = gilrs.next_event() {
+        match event.event {
+            EventType::ButtonPressed(button, _) => {
+                pad.pressed.push(button);
+                if !pad.down.contains(&button) {
+                    pad.down.push(button);
+                }
+            },
+            EventType::ButtonReleased(button, _) => {
+                pad.released.push(button);
+                pad.down.retain(|b| *b != button);
+            },
+            EventType::Disconnected => pad.down.clear(),
+            _ => {}
+        }
+    }
+}
+
+fn binding_state(b: &Binding, pad: &PadState) -> (bool, bool, bool) {
+    return match b {
+        Binding::Key(k) => (is_key_pressed(*k), is_key_released(*k), is_key_down(*k)),
+        Binding::Mouse(m) => (
+            is_mouse_button_pressed(*m), is_mouse_button_released(*m), is_mouse_button_down(*m)
+        ),
+        Binding::Pad(p) => (pad.pressed.contains(p), pad.released.contains(p), pad.down.contains(p))
+    };
+}
+
+fn is_navigation(action: Action) -> bool {
+    return matches!(
+        action,
+        Action::NextSlot | Action::PrevSlot
+            | Action::NavUp | Action::NavDown | Action::NavLeft | Action::NavRight
+    );
+}
+
+pub fn input_spin(pad: &PadState) {
+    let mouse = mouse_screen();
+    for (_, map) in world_mut().query_mut::<&mut InputMap>().into_iter() {
+        map.pressed.clear();
+        map.released.clear();
+        map.down.clear();
+
+        let mut used_mouse = mouse != map.last_mouse;
+        map.last_mouse = mouse;
+
+        for (action, binding) in map.bindings.iter() {
+            let (pressed, released, down) = binding_state(binding, pad);
+            if pressed { map.pressed.push(*action); }
+            if released { map.released.push(*action); }
+            if down { map.down.push(*action); }
+            if pressed && matches!(binding, Binding::Mouse(_)) {
+                used_mouse = true;
+            }
+        }
+
+        if map.pressed.iter().any(|a| is_navigation(*a)) {
+            map.pointer_mode = PointerMode::Focus;
+        } else if used_mouse {
+            map.pointer_mode = PointerMode::Mouse;
+        }
+    }
+}
+
+pub fn is_action_pressed(action: Action) -> bool {
+    for (_, map) in world().query::<&InputMap>().iter() {
+        return map.pressed.contains(&action);
+    }
+
+    return false;
+}
+
+pub fn is_action_released(action: Action) -> bool {
+    for (_, map) in world().query::<&InputMap>().iter() {
+        return map.released.contains(&action);
+    }
+
+    return false;
+}
+
+pub fn is_action_down(action: Action) -> bool {
+    for (_, map) in world().query::<&InputMap>().iter() {
+        return map.down.contains(&action);
+    }
+
+    return false;
+}
+
+pub fn pointer_mode() -> PointerMode {
+    for (_, map) in world().query::<&InputMap>().iter() {
+        return map.pointer_mode;
+    }
+
+    return PointerMode::Mouse;
+}
+
+pub fn focused_entity() -> Option<Entity> {
+    for (id, _) in world().query::<&Focused>().iter() {
+        return Some(id);
+    }
+
+    return None;
+}
+
+/// World position the player is pointing at: the cursor, or the centre of the
+/// focused clickbox while navigating with keys or a gamepad.
+pub fn pointer_world() -> Vec2 {
+    if pointer_mode() == PointerMode::Focus {
+        if let Some(p) = focused_entity().and_then(world_clickbox_center) {
+            return p;
+        }
+    }
+
+    return mouse_world();
+}
+
+fn focusable_entities() -> Vec<(Entity, Vec2)> {
+    let mut out = vec![];
+    let w = world();
+    for (id, _) in w.query::<&component::ClickBox>().iter() {
+        let is_interactable = w.get::<&component::Pickup>(id).is_ok()
+            || w.get::<&component::IsBook>(id).is_ok()
+            || w.get::<&component::IsDropoff>(id).is_ok();
+        if !is_interactable {
+            continue;
+        }
+        if let Some(p) = world_clickbox_center(id) {
+            out.push((id, p));
+        }
+    }
+
+    // Reading order, so NextSlot walks the shelves left to right, top to bottom.
+    out.sort_by(|a, b| b.1.y.total_cmp(&a.1.y).then(a.1.x.total_cmp(&b.1.x)));
+    return out;
+}
+
+fn step_slot(candidates: &Vec<(Entity, Vec2)>, current: Option<Entity>, step: i32) -> Option<Entity> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let n = candidates.len() as i32;
+    let index = match current.and_then(|c| candidates.iter().position(|(id, _)| *id == c)) {
+        None => if step < 0 { n - 1 } else { 0 },
+        Some(i) => (i as i32 + step).rem_euclid(n)
+    };
+    return Some(candidates[index as usize].0);
+}
+
+/// `origin` is the centre of `current`, if it still has a clickbox.
+fn step_direction(candidates: &Vec<(Entity, Vec2)>, current: Option<Entity>, origin: Option<Vec2>, dir: Vec2) -> Option<Entity> {
+    let origin = match origin {
+        None => return step_slot(candidates, current, 1),
+        Some(p) => p
+    };
+
+    let mut best: Option<(f32, Entity)> = None;
+    for (id, p) in candidates.iter() {
+        if Some(*id) == current {
+            continue;
+        }
+        let d = *p - origin;
+        let along = d.dot(dir);
+        if along <= 0.0 {
+            continue;
+        }
+        // Penalise sideways drift so "right" prefers the item on the same shelf.
+        let across = (d - dir * along).length();
+        let score = along + 2.0 * across;
+        if best.map_or(true, |(s, _)| score < s) {
+            best = Some((score, *id));
+        }
+    }
+
+    return match best {
+        None => current,
+        Some((_, id)) => Some(id)
+    };
+}
+
+pub fn focus_spin() {
+    let current = focused_entity();
+    let candidates = focusable_entities();
+    let origin = current.and_then(world_clickbox_center);
+
+    let next = if is_action_pressed(Action::NextSlot) {
+        step_slot(&candidates, current, 1)
+    } else if is_action_pressed(Action::PrevSlot) {
+        step_slot(&candidates, current, -1)
+    } else if is_action_pressed(Action::NavUp) {
+        step_direction(&candidates, current, origin, vec2(0.0, 1.0))
+    } else if is_action_pressed(Action::NavDown) {
+        step_direction(&candidates, current, origin, vec2(0.0, -1.0))
+    } else if is_action_pressed(Action::NavLeft) {
+        step_direction(&candidates, current, origin, vec2(-1.0, 0.0))
+    } else if is_action_pressed(Action::NavRight) {
+        step_direction(&candidates, current, origin, vec2(1.0, 0.0))
+    } else {
+        return;
+    };
+
+    if next == current {
+        return;
+    }
+    if let Some(c) = current {
+        commands().remove_one::<Focused>(c);
+    }
+    if let Some(n) = next {
+        commands().insert_one(n, Focused {});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(i: u64) -> Entity {
+        return Entity::from_bits((1 << 32) | i).unwrap();
+    }
+
+    /// Two shelves of two slots, in the reading order `focusable_entities` sorts into.
+    fn shelves() -> Vec<(Entity, Vec2)> {
+        return vec![
+            (entity(0), vec2(0.0, 10.0)),
+            (entity(1), vec2(10.0, 10.0)),
+            (entity(2), vec2(0.0, 0.0)),
+            (entity(3), vec2(10.0, 0.0))
+        ];
+    }
+
+    #[test]
+    fn step_slot_wraps_around() {
+        let c = shelves();
+        assert_eq!(step_slot(&c, Some(entity(3)), 1), Some(entity(0)));
+        assert_eq!(step_slot(&c, Some(entity(0)), -1), Some(entity(3)));
+        assert_eq!(step_slot(&c, Some(entity(1)), 1), Some(entity(2)));
+    }
+
+    #[test]
+    fn step_slot_starts_from_either_end_without_focus() {
+        let c = shelves();
+        assert_eq!(step_slot(&c, None, 1), Some(entity(0)));
+        assert_eq!(step_slot(&c, None, -1), Some(entity(3)));
+    }
+
+    #[test]
+    fn nothing_to_focus_without_clickboxes() {
+        assert_eq!(step_slot(&vec![], None, 1), None);
+        assert_eq!(step_direction(&vec![], None, None, vec2(1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn step_direction_prefers_the_same_shelf() {
+        let mut c = shelves();
+        // Nearer along the way, but a shelf down.
+        c.push((entity(4), vec2(6.0, 4.0)));
+        let from = Some(vec2(0.0, 10.0));
+        assert_eq!(step_direction(&c, Some(entity(0)), from, vec2(1.0, 0.0)), Some(entity(1)));
+        assert_eq!(step_direction(&c, Some(entity(0)), from, vec2(0.0, -1.0)), Some(entity(2)));
+    }
+
+    #[test]
+    fn step_direction_stays_put_at_the_edge() {
+        let c = shelves();
+        assert_eq!(step_direction(&c, Some(entity(1)), Some(vec2(10.0, 10.0)), vec2(1.0, 0.0)), Some(entity(1)));
+    }
+
+    #[test]
+    fn step_direction_without_focus_takes_the_first_slot() {
+        assert_eq!(step_direction(&shelves(), None, None, vec2(0.0, -1.0)), Some(entity(0)));
+    }
+
+    #[test]
+    fn every_named_binding_round_trips() {
+        let all = KEY_NAMES.iter().map(|(_, k)| Binding::Key(*k))
+            .chain(MOUSE_NAMES.iter().map(|(_, m)| Binding::Mouse(*m)))
+            .chain(PAD_NAMES.iter().map(|(_, p)| Binding::Pad(*p)));
+        for b in all {
+            let s = binding_to_string(&b).unwrap();
+            assert_eq!(binding_from_string(&s), Some(b), "{}", s);
+        }
+    }
+
+    #[test]
+    fn default_bindings_can_be_saved() {
+        for (action, b) in default_bindings() {
+            assert!(binding_to_string(&b).is_some(), "{:?} has an unnamed binding {:?}", action, b);
+        }
+    }
+
+    #[test]
+    fn reads_settings_file_names() {
+        assert_eq!(binding_from_string("key:Space"), Some(Binding::Key(KeyCode::Space)));
+        assert_eq!(binding_from_string("pad:South"), Some(Binding::Pad(Button::South)));
+    }
+
+    #[test]
+    fn rebinding_a_key_keeps_other_devices() {
+        let mut bindings = default_bindings();
+        replace_binding(&mut bindings, Action::Select, Binding::Key(KeyCode::E));
+
+        let select = bindings.iter().filter(|(a, _)| *a == Action::Select).map(|(_, b)| *b).collect::<Vec<_>>();
+        assert_eq!(select, vec![
+            Binding::Mouse(MouseButton::Left),
+            Binding::Pad(Button::South),
+            Binding::Key(KeyCode::E)
+        ]);
+    }
+
+    #[test]
+    fn select_keeps_the_left_mouse_button() {
+        let mut bindings = default_bindings();
+        replace_binding(&mut bindings, Action::Select, Binding::Mouse(MouseButton::Middle));
+
+        assert!(bindings.contains(&(Action::Select, Binding::Mouse(MouseButton::Left))));
+        assert!(bindings.contains(&(Action::Select, Binding::Mouse(MouseButton::Middle))));
+    }
+
+    #[test]
+    fn rebinding_other_actions_replaces_the_same_device() {
+        let mut bindings = default_bindings();
+        replace_binding(&mut bindings, Action::Cancel, Binding::Mouse(MouseButton::Middle));
+
+        assert!(!bindings.contains(&(Action::Cancel, Binding::Mouse(MouseButton::Right))));
+        assert!(bindings.contains(&(Action::Cancel, Binding::Key(KeyCode::Back))));
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(binding_from_string("key:Hyper"), None);
+        assert_eq!(binding_from_string("wheel:Up"), None);
+        assert_eq!(binding_from_string("Space"), None);
+    }
+}
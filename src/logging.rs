@@ -0,0 +1,179 @@
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Filter spec, e.g. `info` or `warn,aseprite_loader=debug,system=trace`.
+pub const LOG_ENV: &str = "ASMODEUS_LOG";
+/// Optional path of a file that receives a copy of every log line.
+pub const LOG_FILE_ENV: &str = "ASMODEUS_LOG_FILE";
+
+const CRATE_PREFIX: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+pub struct Directive {
+    pub target: Option<String>,
+    pub level: LevelFilter
+}
+
+pub struct GameLogger {
+    directives: Vec<Directive>,
+    file: Option<Mutex<File>>
+}
+
+fn parse_level(s: &str) -> Option<LevelFilter> {
+    return match s.trim().to_ascii_lowercase().as_str() {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None
+    };
+}
+
+pub fn parse_directives(spec: &str) -> Vec<Directive> {
+    let mut directives = vec![];
+    for part in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let directive = match part.split_once('=') {
+            None => match parse_level(part) {
+                Some(level) => Directive { target: None, level },
+                None => Directive { target: Some(part.to_string()), level: LevelFilter::Trace }
+            },
+            Some((target, level)) => match parse_level(level) {
+                Some(level) => Directive { target: Some(target.trim().to_string()), level },
+                None => {
+                    eprintln!("Ignoring log directive with unknown level: {}", part);
+                    continue;
+                }
+            }
+        };
+        directives.push(directive);
+    }
+
+    return directives;
+}
+
+fn matches_target(directive: &str, target: &str) -> bool {
+    // Our own modules can be named without the crate prefix.
+    let short = target.strip_prefix(CRATE_PREFIX).unwrap_or(target);
+    for t in [target, short] {
+        if t == directive || t.starts_with(&format!("{}::", directive)) {
+            return true;
+        }
+    }
+
+    return false;
+}
+
+impl GameLogger {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        // The most specific matching target wins over the global level.
+        let mut best: Option<(usize, LevelFilter)> = None;
+        let mut global = LevelFilter::Warn;
+        for d in self.directives.iter() {
+            match &d.target {
+                None => global = d.level,
+                Some(t) => {
+                    if matches_target(t, target) && best.map_or(true, |(len, _)| len < t.len()) {
+                        best = Some((t.len(), d.level));
+                    }
+                }
+            }
+        }
+
+        return best.map_or(global, |(_, level)| level);
+    }
+}
+
+impl Log for GameLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        return metadata.level() <= self.level_for(metadata.target());
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let target = record.target().strip_prefix(CRATE_PREFIX).unwrap_or(record.target());
+        let line = format!("[{:<5} {}] {}", record.level(), target, record.args());
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}", line),
+            _ => println!("{}", line)
+        }
+
+        if let Some(file) = &self.file {
+            if let Ok(mut f) = file.lock() {
+                let _ = writeln!(f, "{}", line);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut f) = file.lock() {
+                let _ = f.flush();
+            }
+        }
+    }
+}
+
+/// Installs the game logger, configured from `ASMODEUS_LOG` and
+/// `ASMODEUS_LOG_FILE`. Only the first logger installed in the process takes effect.
+pub fn init() {
+    let spec = env::var(LOG_ENV).unwrap_or("info".to_string());
+    let directives = parse_directives(&spec);
+
+    let file = match env::var(LOG_FILE_ENV) {
+        Err(_) => None,
+        Ok(path) => match File::create(&path) {
+            Ok(f) => Some(Mutex::new(f)),
+            Err(e) => {
+                eprintln!("Could not open log file {}: {}", path, e);
+                None
+            }
+        }
+    };
+
+    let logger = GameLogger { directives, file };
+    let has_global = logger.directives.iter().any(|d| d.target.is_none());
+    let mut max_level = logger.directives.iter().map(|d| d.level).max().unwrap_or(LevelFilter::Warn);
+    if !has_global {
+        max_level = max_level.max(LevelFilter::Warn);
+    }
+
+    match log::set_boxed_logger(Box::new(logger)) {
+        Ok(()) => log::set_max_level(max_level),
+        Err(_) => eprintln!("A logger is already installed; {} is ignored", LOG_ENV)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(spec: &str) -> Vec<(Option<String>, LevelFilter)> {
+        return parse_directives(spec).into_iter().map(|d| (d.target, d.level)).collect();
+    }
+
+    #[test]
+    fn parses_global_and_target_levels() {
+        assert_eq!(parsed("info, aseprite_loader=debug,system=TRACE"), vec![
+            (None, LevelFilter::Info),
+            (Some("aseprite_loader".to_string()), LevelFilter::Debug),
+            (Some("system".to_string()), LevelFilter::Trace)
+        ]);
+    }
+
+    #[test]
+    fn bare_target_enables_everything_for_it() {
+        assert_eq!(parsed("audio"), vec![(Some("audio".to_string()), LevelFilter::Trace)]);
+    }
+
+    #[test]
+    fn skips_empty_parts_and_unknown_levels() {
+        assert_eq!(parsed(",warn,,audio=loud, "), vec![(None, LevelFilter::Warn)]);
+    }
+}
@@ -1,51 +1,137 @@
-use aseprite_loader::{load_aseprite_atlas_bytes, ImageAtlas, draw_frame, find_first_frame_in_tag};
+use aseprite_loader::{load_aseprite_atlas_bytes, ImageAtlas};
 use component::{IsBook, Demon};
+use render::{RenderLayer, RenderQueue};
+use atlas_names::tags;
 use system::world_clickbox_from_id;
 use std::path::Path;
 use comfy::*;
+use log::{info, warn};
 
 use crate::component::Pickup;
-mod aseprite_loader;
 
 mod system;
 mod component;
+mod demon_ai;
+mod render;
+mod camera;
+mod input;
+mod events;
+mod recipe_data;
+mod tooltip;
+mod dev_overlay;
+mod logging;
+mod audio;
+mod settings;
+mod strings;
+mod ui_text;
+mod effects;
+mod animation;
+mod atlas_names;
+mod assets;
+mod mods;
 
 simple_game!("Asmodeus Web Summons", GameState, config, setup, update);
 
 pub struct GameState {
     atlas: Option<ImageAtlas>,
+    render_queue: RenderQueue,
+    gilrs: Option<gilrs::Gilrs>,
+    pad: input::PadState,
 }
 
 impl GameState {
     pub fn new(_c: &mut EngineState) -> Self {
-        Self { atlas: None }
+        Self {
+            atlas: None,
+            render_queue: RenderQueue::new(),
+            gilrs: gilrs::Gilrs::new().ok(),
+            pad: input::new_pad_state(),
+        }
     }
 }
 
 fn config(config: GameConfig) -> GameConfig {
-    GameConfig {resolution: ResolutionConfig::Logical(1280, 720), ..config }
+    logging::init();
+    // Runs before the window is created, so display settings apply here.
+    settings::apply_to_config(&settings::startup_settings(), config)
 }
 
 fn setup(gs: &mut GameState, _c: &mut EngineContext) {
-    let mut m = main_camera_mut();
-    m.zoom = screen_width() / 4.0;
+    let user_settings = settings::startup_settings();
 
-    let json_bytes = include_bytes!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/target/atlas.json"
-    ));
-    let png_bytes = include_bytes!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/target/atlas.png"
-    ));
+    let mut input_map = input::new_input_map();
+    input_map.bindings = settings::bindings_from_settings(&user_settings);
 
-    let atlas = load_aseprite_atlas_bytes(_c, json_bytes, png_bytes);
+    commands().spawn((camera::new_camera_rig(),));
+    commands().spawn((input_map,));
+    commands().spawn((events::new_event_bus(),));
+    commands().spawn((animation::new_animation_events(),));
+    commands().spawn((dev_overlay::new_dev_overlay(),));
 
-    let frame = find_first_frame_in_tag(&atlas, &"background/background".to_string()).unwrap();
-    spawn_level(frame);
+    let json_bytes = assets::read(assets::ATLAS_JSON);
+    let png_bytes = assets::read(assets::ATLAS_PNG);
 
+    let mut atlas = load_aseprite_atlas_bytes(_c, &json_bytes, &png_bytes);
+    let loaded_mods = mods::discover_mods(&mods::mods_dir());
+    mods::load_mod_atlases(_c, &mut atlas, &loaded_mods);
+    info!("Loaded atlas with {} frames and {} tags", atlas.frames.len(), atlas.tags.len());
+
+    let mut recipes = recipe_data::load_recipe_data_bytes(&assets::read(assets::RECIPES_JSON));
+    mods::merge_mod_data(&mut recipes, &loaded_mods);
+
+    let background = atlas.tag_handle(tags::BACKGROUND_BACKGROUND).unwrap();
+    let frame = atlas.first_frame(background).unwrap();
+    spawn_level(frame, &recipes);
+
+    let mod_levels = mods::mod_level_tags(&atlas, &loaded_mods);
+    for h in mod_levels.iter() {
+        if let Some(f) = atlas.first_frame(*h) {
+            spawn_level(f, &recipes);
+        }
+    }
+
+    let tag_table = render::new_tag_table(&atlas, &recipes);
+
+    let mut mixer = audio::new_audio_mixer();
+    mixer.music_volume = user_settings.music_volume;
+    mixer.sfx_volume = user_settings.sfx_volume;
+    audio::load_sounds(&mut mixer, &recipes);
+    commands().spawn((mixer,));
+    commands().spawn((recipes,));
+    commands().spawn((effects::load_effect_library_bytes(&assets::read(assets::EFFECTS_JSON), &atlas),));
+    let mut string_table = strings::load_string_tables(&user_settings.language);
+    mods::merge_mod_strings(&mut string_table, &loaded_mods);
+    commands().spawn((string_table,));
+    commands().spawn((user_settings, settings::new_settings_menu()));
+
+    commands().spawn((tag_table,));
     gs.atlas = Some(atlas);
 
+    commands().spawn(
+        (
+            component::Sprite {
+                tag: background,
+                art_layer: None,
+                layer: RenderLayer::Background,
+                sub_order: 0
+            },
+        )
+    );
+
+    // Mod shelves and props are drawn over the background.
+    for (i, h) in mod_levels.iter().enumerate() {
+        commands().spawn(
+            (
+                component::Sprite {
+                    tag: *h,
+                    art_layer: None,
+                    layer: RenderLayer::Background,
+                    sub_order: 1 + i as i32
+                },
+            )
+        );
+    }
+
     commands().spawn(
         (
             component::GlobalGameState{
@@ -58,19 +144,17 @@ fn setup(gs: &mut GameState, _c: &mut EngineContext) {
 }
 
 fn properties_from_aseprite_data(
-        world: &mut  AtomicRefMut<'static, World>, id: Entity, value: &serde_json::Value
+        world: &mut  AtomicRefMut<'static, World>,
+        id: Entity,
+        value: &serde_json::Value,
+        recipes: &recipe_data::RecipeData
 ) {
     let _pickup_res = match value["pickup"].as_str() {
         None => Ok(()),
-        Some(s) => match s {
-            "web" => world.insert_one(id, Pickup::Web {  }),
-            "skull" => world.insert_one(id, Pickup::Skull {  }),
-            "fire" => world.insert_one(id, Pickup::Fire {  }),
-            "knife" => world.insert_one(id, Pickup::Knife {  } ),
-            "booze" => world.insert_one(id, Pickup::Booze {  }),
-            "eye" => world.insert_one(id, Pickup::Eye {  }),
-            "tentacle" => world.insert_one(id, Pickup::Tentacle {  }),
-            _ => Ok(())
+        Some(s) if recipes.ingredients.contains_key(s) => world.insert_one(id, Pickup { id: s.to_string() }),
+        Some(s) => {
+            warn!("Slice pickup {} is not an ingredient in the recipe data", s);
+            Ok(())
         }
     };
 
@@ -93,7 +177,7 @@ fn properties_from_aseprite_data(
     };
 }
 
-fn spawn_level(frame: &aseprite_loader::Frame) {
+fn spawn_level(frame: &aseprite_loader::Frame, recipes: &recipe_data::RecipeData) {
     let mut w = world_mut();
     for s in frame.slices.iter() {
         let id = w.reserve_entity();
@@ -109,195 +193,119 @@ fn spawn_level(frame: &aseprite_loader::Frame) {
                         (s.bound.x - frame.source_size.w / 2) as f32,
                         (-s.bound.y + frame.source_size.h / 2 - s.bound.h ) as f32
                     )
-                }
+                },
+                component::SliceColor{ color: s.color }
             )
         );
         
-        properties_from_aseprite_data(&mut w, id, &s.data);
-    }
-}
+        properties_from_aseprite_data(&mut w, id, &s.data, recipes);
 
-fn should_show_book() -> bool {
-    for (id, gs) in world().query::<&component::GlobalGameState>().iter() {
-        if gs.show_recipe_book {
-            return true;
+        if let Some(t) = tooltip::tooltip_for_slice(&w, id, &s.data, recipes) {
+            let _tooltip_result = w.insert_one(id, t);
         }
-    }
-
-    return false;
-}
 
-fn should_draw_pickup() -> bool {
-    for (id, gs) in world().query::<&component::GlobalGameState>().iter() {
-        if gs.pickup.is_some() {
-            return true;
+        if s.data["alpha_hit"].as_bool().unwrap_or(false) {
+            let _alpha_result = w.insert_one(
+                id, component::AlphaHit{ frame: frame.index, origin: vec2(0.0, 0.0) }
+            );
         }
     }
-
-    return false;
-}
-
-fn pickup_frame_string_from_state() -> Option<String> {
-    for (id, gs) in world().query::<& component::GlobalGameState>().iter() {
-        return match &gs.pickup {
-            None => None,
-            Some(p) => pickup_frame_string_from_enum(p)
-        };
-    }
-
-    return None;
 }
 
-fn pickup_frame_string_from_enum(p: &component::Pickup) -> Option<String> {
-    return match p {
-        Pickup::Web {  } => Some("pickups/web".to_string()),
-        Pickup::Tentacle {  } => Some("pickups/tentacle".to_string()),
-        Pickup::Eye {  } => Some("pickups/eye".to_string()),
-        Pickup::Fire {  } => Some("pickups/fire".to_string()),
-        Pickup::Skull {  } => Some("pickups/skull".to_string()),
-        Pickup::Knife {  } => Some("pickups/knife".to_string()),
-        Pickup::Booze {  } => Some("pickups/booze".to_string()),
-        _ => None
-    };
-}
-
-fn demon_frame_string_from_enum(d: &component::Demon) -> Option<String> {
-    return match d {
-        Demon::ToothImp {  } => Some("demons/tooth_imp".to_string()),
-        _ => None
-    };
-}
-
-fn draw_icon(gs: &GameState, pos: Vec2) {
-    let atlas = gs.atlas.as_ref().unwrap();
-    let maybe_framekey = pickup_frame_string_from_state();
-    if maybe_framekey.is_none() {
-        return;
+fn queue_held_pickup(queue: &mut RenderQueue) {
+    for (id, gs) in world().query::<&component::GlobalGameState>().iter() {
+        let maybe_framekey = gs.pickup.as_ref().and_then(render::pickup_tag_handle);
+        if maybe_framekey.is_some() {
+            queue.push(maybe_framekey.unwrap(), input::pointer_world(), RenderLayer::Held, 0);
+        }
     }
-    let frame = find_first_frame_in_tag(atlas, &maybe_framekey.unwrap()).unwrap();
-    draw_frame(atlas, frame, pos, 10);
 }
 
-fn find_draw_circle_location() -> Option<Vec2> {
-    for (id, _) in world().query::<&component::IsSummonCircle>().iter() {
-        let maybe_box = world_clickbox_from_id(id);
-        if (maybe_box.is_some()) {
-            let b = maybe_box.unwrap();
-            return Some(vec2(b.pos.x + b.size.x * 0.5, b.pos.y + b.size.y * 0.5));
+fn queue_recipe_book(queue: &mut RenderQueue) {
+    for (id, gs) in world().query::<&component::GlobalGameState>().iter() {
+        if let (true, Some(book)) = (gs.show_recipe_book, render::book_tag_handle()) {
+            queue.push(book, vec2(0.0, 0.0), RenderLayer::Book, 0);
         }
     }
-
-    return None;
 }
 
-fn draw_recipe_stack_from_stack(gs: &GameState, recipe_stack: &Vec<component::Pickup>) {
-    let maybe_p = find_draw_circle_location();
+fn queue_recipe_stack(queue: &mut RenderQueue) {
+    let maybe_p = ui_text::summon_circle_center();
     if maybe_p.is_none() {
         return;
     }
     let p = maybe_p.unwrap();
-    let atlas = gs.atlas.as_ref().unwrap();
-    for (i, r) in recipe_stack.iter().enumerate() {
-        let maybe_frame_key = pickup_frame_string_from_enum(r);
-        if maybe_frame_key.is_none() {
-            continue;
-        }
-        let o = vec2(0.0, 10.0 * (i + 1) as f32);
-        let frame = find_first_frame_in_tag(atlas, &maybe_frame_key.unwrap());
-        draw_frame(atlas, frame.unwrap(), p + o, 30);
-    }
-}
-
-fn draw_recipe_stack(gs: &GameState) {
+    let total = recipe_data::recipe_length();
     for (id, global_gs) in world().query::<&component::GlobalGameState>().iter() {
-        draw_recipe_stack_from_stack(gs, &global_gs.recipe_stack);
+        for (i, r) in global_gs.recipe_stack.iter().enumerate() {
+            let maybe_frame_key = render::pickup_tag_handle(r);
+            if maybe_frame_key.is_none() {
+                continue;
+            }
+            let slot = ui_text::slot_position(p, i, total);
+            queue.push(maybe_frame_key.unwrap(), slot, RenderLayer::RecipeStack, i as i32);
+        }
     }
 }
 
-fn draw_demons(gs: &GameState) {
-    let atlas = gs.atlas.as_ref().unwrap();
-    let w = world();
-    for (id, demon) in w.query::<&component::Demon>().iter() {
-        let maybe_demon_string = demon_frame_string_from_enum(demon);
-        if maybe_demon_string.is_none() {
-            continue;
-        }
-
-        let pos = match w.get::<&component::Motion>(id) {
-            Err(e) => vec2(0.0, 0.0),
-            Ok(m) => m.position
-        };
-
-        let maybe_frame = find_first_frame_in_tag(atlas, &maybe_demon_string.unwrap());
-        if maybe_frame.is_none() {
-            continue;
+fn queue_carried_pickups(queue: &mut RenderQueue) {
+    for (id, (brain, motion)) in world().query::<(&component::DemonBrain, &component::Motion)>().iter() {
+        let maybe_carried_key = demon_ai::carried_pickup(brain)
+            .and_then(|p| render::pickup_tag_handle(&p));
+        if maybe_carried_key.is_some() {
+            queue.push(maybe_carried_key.unwrap(), motion.position + vec2(0.0, 12.0), RenderLayer::Demons, 1);
         }
-        draw_frame(atlas, maybe_frame.unwrap(), pos, 40);
     }
 }
 
-fn should_draw_no() -> bool {
-    for (id, no) in world().query::<&component::DrawNo>().iter() {
-        return true;
-    }
-
-    return false;
-}
+fn update(gs: &mut GameState, _c: &mut EngineContext) {
+    // Cleared before any gate, so systems that run while the settings menu
+    // is open never see the previous frame's events.
+    events::begin_frame();
 
-fn should_draw_win() -> bool {
-    for (id, win) in world().query::<&component::DrawWin>().iter() {
-        return true;
+    if let Some(g) = gs.gilrs.as_mut() {
+        input::poll_gamepad(g, &mut gs.pad);
     }
-    return false;
-}
+    input::input_spin(&gs.pad);
 
-fn update(gs: &mut GameState, _c: &mut EngineContext) {
-    if is_key_pressed(KeyCode::Escape) {
+    if input::is_action_pressed(input::Action::Quit) || settings::settings_spin(&gs.pad) {
         *_c.quit_flag = true;
     }
-    
-    let atlas = gs.atlas.as_ref().unwrap();
-
-    let frame = find_first_frame_in_tag(atlas, &"background/background".to_string()).unwrap();
-    draw_frame(atlas, frame, vec2(0.0, 0.0), 0);
 
-    let ui_frame = find_first_frame_in_tag(atlas, &"GUI/ui".to_string()).unwrap();
-    //draw_frame(atlas, ui_frame, vec2(0.0, 0.0), 1);
+    let atlas = gs.atlas.as_ref().unwrap();
 
     // Update 
-    system::clickable_spin();
-    system::pickup_spin();
-    system::interactable_spin();
-    system::recipe_book_spin();
-    system::recipe_stack_spin();
-    system::Lifetime_spin();
-    system::motion_spin();
-
-    if should_draw_pickup() {
-        draw_icon(gs, mouse_world());
-    }
-    
-    // Debug draw
-    // system::clickable_draw();
-
-    if should_show_book() {
-        let book_frame = find_first_frame_in_tag(atlas, &"book/book".to_string()).unwrap();
-        draw_frame(atlas, book_frame, vec2(0.0, 0.0), 2);
-    }
-
-    //draw_recipe_stack_from_stack(gs, &recipe_stack);
-    draw_recipe_stack(gs);
-    draw_demons(gs);
-
-    if should_draw_no() {
-        let no_frames = find_first_frame_in_tag(atlas, &"no/no".to_string()).unwrap();
-        draw_frame(atlas, no_frames, vec2(0.0, 0.0), 100);
+    camera::camera_spin();
+    if !settings::is_menu_open() {
+        input::focus_spin();
+        system::clickable_spin(atlas);
+        system::interactable_spin();
+        system::recipe_stack_spin();
     }
-
-    if should_draw_win() {
-        let win_frames = find_first_frame_in_tag(atlas, &"no/win".to_string()).unwrap();
-        draw_frame(atlas, win_frames, vec2(0.0, 0.0), 100);
+    animation::animation_spin(atlas);
+    system::pending_summon_spin();
+    audio::audio_spin();
+    if system::Lifetime_spin() {
+        info!("Summon complete, quitting");
+        *_c.quit_flag = true;
     }
+    demon_ai::demon_ai_spin();
+    system::motion_spin();
+    effects::effects_spin();
+
+    render::queue_sprites(&mut gs.render_queue);
+    queue_held_pickup(&mut gs.render_queue);
+    queue_recipe_book(&mut gs.render_queue);
+    queue_recipe_stack(&mut gs.render_queue);
+    queue_carried_pickups(&mut gs.render_queue);
+
+    render::draw_render_queue(atlas, &mut gs.render_queue);
+    system::focus_draw();
+    ui_text::slot_labels_draw();
+    ui_text::messages_draw();
+    tooltip::tooltip_draw();
+    dev_overlay::dev_overlay_spin();
+    camera::draw_letterbox();
 }
 
 
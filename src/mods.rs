@@ -0,0 +1,383 @@
+//! Mods live in folders under the mods directory, each with a `mod.json`
+//! manifest:
+//!
+//! ```json
+//! {
+//!     "id": "spider_shelf",
+//!     "name": "Spider Shelf",
+//!     "load_after": ["more_bones"],
+//!     "atlas": "atlas.json",
+//!     "level_tag": "spider_shelf/level",
+//!     "data": "recipes.json",
+//!     "strings": { "en": "strings/en.json" }
+//! }
+//! ```
+//!
+//! Every field but `id` is optional. `data` uses the `recipes.json` format
+//! with every section optional, so a mod can add ingredients, recipes,
+//! demons and sounds. Art follows the base game's tag names:
+//! `pickups/<ingredient>` and `demons/<demon>`. The first frame of
+//! `level_tag` is drawn over the background, and its slices are added to the
+//! level like the base game's, which is how new ingredients reach the shelf.
+//!
+//! Mods load in id order, moved after the mods named in `load_after`. When
+//! two sources define the same id, the later one wins and the conflict is
+//! logged; the base game always comes first.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use comfy::*;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use aseprite_loader::{ImageAtlas, TagHandle, load_atlas_page};
+use crate::recipe_data::{DemonDef, RecipeData, RecipeDef, SoundDef, TooltipDef};
+use crate::strings::StringTable;
+
+/// Folder scanned for mods. Defaults to `mods` in the working directory.
+pub const MODS_DIR_ENV: &str = "ASMODEUS_MODS_DIR";
+
+const MANIFEST: &str = "mod.json";
+const BASE_GAME: &str = "the base game";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModManifest {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Ids of mods that must load before this one.
+    #[serde(default)]
+    pub load_after: Vec<String>,
+    /// Aseprite export; its image is found next to it.
+    #[serde(default)]
+    pub atlas: Option<String>,
+    /// Tag whose first frame adds art and slices to the level.
+    #[serde(default)]
+    pub level_tag: Option<String>,
+    #[serde(default)]
+    pub data: Option<String>,
+    /// String table paths keyed by language code.
+    #[serde(default)]
+    pub strings: HashMap<String, String>
+}
+
+/// The parts of `recipes.json` a mod can add to.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ModData {
+    #[serde(default)]
+    pub ingredients: HashMap<String, TooltipDef>,
+    #[serde(default)]
+    pub recipes: Vec<RecipeDef>,
+    #[serde(default)]
+    pub demons: HashMap<String, DemonDef>,
+    /// Paths are relative to the mod folder.
+    #[serde(default)]
+    pub sounds: HashMap<String, SoundDef>
+}
+
+pub struct LoadedMod {
+    pub manifest: ModManifest,
+    /// Absolute, so paths built from it do not depend on the asset directory.
+    pub dir: PathBuf
+}
+
+pub fn mods_dir() -> PathBuf {
+    return PathBuf::from(env::var(MODS_DIR_ENV).unwrap_or("mods".to_string()));
+}
+
+fn read_manifest(dir: &Path) -> Result<ModManifest, String> {
+    let path = dir.join(MANIFEST);
+    let bytes = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    return serde_json::from_slice(&bytes).map_err(|e| format!("{}: {}", path.display(), e));
+}
+
+/// Reads every manifest under `root` and returns the mods in load order.
+/// Unreadable manifests, duplicate ids and unmet `load_after` entries are
+/// logged and the mod skipped.
+pub fn discover_mods(root: &Path) -> Vec<LoadedMod> {
+    let entries = match fs::read_dir(root) {
+        Err(_) => {
+            info!("No mods folder at {}", root.display());
+            return vec![];
+        },
+        Ok(e) => e
+    };
+
+    let mut dirs = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join(MANIFEST).is_file())
+        .collect_vec();
+    dirs.sort();
+
+    let mut found: Vec<LoadedMod> = vec![];
+    for dir in dirs {
+        let manifest = match read_manifest(&dir) {
+            Err(e) => {
+                warn!("Skipping mod with unreadable manifest {}", e);
+                continue;
+            },
+            Ok(m) => m
+        };
+
+        if let Some(other) = found.iter().find(|m| m.manifest.id == manifest.id) {
+            warn!(
+                "Skipping mod {} in {}: the id is taken by {}",
+                manifest.id, dir.display(), other.dir.display()
+            );
+            continue;
+        }
+
+        let dir = fs::canonicalize(&dir).unwrap_or(dir);
+        found.push(LoadedMod { manifest, dir });
+    }
+
+    found.sort_by(|a, b| a.manifest.id.cmp(&b.manifest.id));
+    return load_order(found);
+}
+
+/// Stable topological order: each mod is placed as early as its id allows
+/// once everything in its `load_after` is placed.
+fn load_order(mut pending: Vec<LoadedMod>) -> Vec<LoadedMod> {
+    let mut ordered: Vec<LoadedMod> = vec![];
+    loop {
+        let ready = pending.iter().position(|m| {
+            m.manifest.load_after.iter().all(|d| ordered.iter().any(|o| &o.manifest.id == d))
+        });
+        match ready {
+            None => break,
+            Some(i) => ordered.push(pending.remove(i))
+        }
+    }
+
+    for m in pending.iter() {
+        warn!(
+            "Skipping mod {}: its load_after {:?} names mods that are missing, skipped or circular",
+            m.manifest.id, m.manifest.load_after
+        );
+    }
+
+    for m in ordered.iter() {
+        info!("Mod {} ({}) from {}", m.manifest.id, m.manifest.name, m.dir.display());
+    }
+
+    return ordered;
+}
+
+/// Adds each mod's atlas as a page of `atlas`.
+pub fn load_mod_atlases(c: &mut EngineContext, atlas: &mut ImageAtlas, mods: &Vec<LoadedMod>) {
+    for m in mods.iter() {
+        let file = match &m.manifest.atlas {
+            None => continue,
+            Some(f) => f
+        };
+
+        let texture = format!("mod:{}", m.manifest.id);
+        match load_atlas_page(c, atlas, &texture, &m.dir.join(file)) {
+            Err(e) => warn!("Mod {} atlas not loaded: {}", m.manifest.id, e),
+            Ok(replaced) => {
+                for tag in replaced {
+                    info!("Mod {} replaces the art of tag {}", m.manifest.id, tag);
+                }
+            }
+        }
+    }
+}
+
+/// Level art and slices added by mods, as tag handles in load order.
+pub fn mod_level_tags(atlas: &ImageAtlas, mods: &Vec<LoadedMod>) -> Vec<TagHandle> {
+    let mut handles = vec![];
+    for m in mods.iter() {
+        if let Some(tag) = &m.manifest.level_tag {
+            match atlas.tag_handle(tag) {
+                None => warn!("Mod {} names unknown level tag {}", m.manifest.id, tag),
+                Some(h) => handles.push(h)
+            }
+        }
+    }
+
+    return handles;
+}
+
+fn merge_defs<T>(
+        kind: &str,
+        into: &mut HashMap<String, T>,
+        from: HashMap<String, T>,
+        mod_id: &str,
+        owners: &mut HashMap<String, String>
+) {
+    for (id, def) in from {
+        let key = format!("{} {}", kind, id);
+        if into.contains_key(&id) {
+            let previous = owners.get(&key).map_or(BASE_GAME, |o| o.as_str());
+            warn!("Mod {} redefines {} from {}; using the mod's", mod_id, key, previous);
+        }
+        into.insert(id, def);
+        owners.insert(key, mod_id.to_string());
+    }
+}
+
+fn merge_recipes(into: &mut Vec<RecipeDef>, from: Vec<RecipeDef>, mod_id: &str, owners: &mut HashMap<String, String>) {
+    for r in from {
+        let key = format!("recipe {}", r.id);
+        if let Some(i) = into.iter().position(|x| x.id == r.id) {
+            let previous = owners.get(&key).map_or(BASE_GAME, |o| o.as_str());
+            warn!("Mod {} redefines {} from {}; using the mod's", mod_id, key, previous);
+            into.remove(i);
+        }
+        owners.insert(key, mod_id.to_string());
+        into.push(r);
+    }
+}
+
+/// Recipes naming unknown ingredients or demons can never be used, and two
+/// recipes with the same ingredients make the second unreachable.
+fn check_recipes(data: &mut RecipeData) {
+    let ingredients = &data.ingredients;
+    let demons = &data.demons;
+    data.recipes.retain(|r| {
+        let unknown = r.ingredients.iter().filter(|i| !ingredients.contains_key(*i)).collect_vec();
+        if !unknown.is_empty() {
+            warn!("Dropping recipe {}: unknown ingredients {:?}", r.id, unknown);
+            return false;
+        }
+        if !demons.contains_key(&r.demon) {
+            warn!("Dropping recipe {}: unknown demon {}", r.id, r.demon);
+            return false;
+        }
+
+        return true;
+    });
+
+    for (i, a) in data.recipes.iter().enumerate() {
+        for b in data.recipes[i + 1..].iter().filter(|b| b.ingredients == a.ingredients) {
+            warn!("Recipes {} and {} use the same ingredients; only {} can be summoned", a.id, b.id, a.id);
+        }
+    }
+}
+
+/// Merges each mod's data file into the base game's recipe data.
+pub fn merge_mod_data(data: &mut RecipeData, mods: &Vec<LoadedMod>) {
+    let mut owners: HashMap<String, String> = HashMap::new();
+    for m in mods.iter() {
+        let file = match &m.manifest.data {
+            None => continue,
+            Some(f) => m.dir.join(f)
+        };
+
+        let parsed: Result<ModData, String> = fs::read(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|b| serde_json::from_slice(&b).map_err(|e| e.to_string()));
+        let mut mod_data = match parsed {
+            Err(e) => {
+                warn!("Mod {} data not loaded from {}: {}", m.manifest.id, file.display(), e);
+                continue;
+            },
+            Ok(d) => d
+        };
+
+        for sound in mod_data.sounds.values_mut() {
+            sound.path = m.dir.join(&sound.path).to_string_lossy().to_string();
+        }
+
+        let id = m.manifest.id.as_str();
+        merge_defs("ingredient", &mut data.ingredients, mod_data.ingredients, id, &mut owners);
+        merge_defs("demon", &mut data.demons, mod_data.demons, id, &mut owners);
+        merge_defs("sound", &mut data.sounds, mod_data.sounds, id, &mut owners);
+        merge_recipes(&mut data.recipes, mod_data.recipes, id, &mut owners);
+    }
+
+    check_recipes(data);
+}
+
+/// Adds each mod's strings to the tables. Mod strings override the base
+/// game's, so a mod can also reword existing text.
+pub fn merge_mod_strings(table: &mut StringTable, mods: &Vec<LoadedMod>) {
+    for m in mods.iter() {
+        for (code, file) in m.manifest.strings.iter() {
+            let path = m.dir.join(file);
+            let parsed: Result<HashMap<String, String>, String> = fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|b| serde_json::from_slice(&b).map_err(|e| e.to_string()));
+            match parsed {
+                Err(e) => warn!("Mod {} strings not loaded from {}: {}", m.manifest.id, path.display(), e),
+                Ok(strings) => table.languages.entry(code.clone()).or_default().extend(strings)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded(id: &str, load_after: &[&str]) -> LoadedMod {
+        let manifest = ModManifest {
+            id: id.to_string(),
+            name: String::new(),
+            load_after: load_after.iter().map(|s| s.to_string()).collect(),
+            atlas: None,
+            level_tag: None,
+            data: None,
+            strings: HashMap::new()
+        };
+        return LoadedMod { manifest, dir: PathBuf::from(id) };
+    }
+
+    fn ids(mods: &Vec<LoadedMod>) -> Vec<&str> {
+        return mods.iter().map(|m| m.manifest.id.as_str()).collect();
+    }
+
+    fn recipe(id: &str, demon: &str) -> RecipeDef {
+        return RecipeDef { id: id.to_string(), ingredients: vec![], demon: demon.to_string(), sound: None };
+    }
+
+    #[test]
+    fn load_order_keeps_id_order_without_dependencies() {
+        let ordered = load_order(vec![loaded("a", &[]), loaded("b", &[]), loaded("c", &[])]);
+        assert_eq!(ids(&ordered), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn load_order_moves_mods_after_their_dependencies() {
+        let ordered = load_order(vec![loaded("a", &["c"]), loaded("b", &[]), loaded("c", &["b"])]);
+        assert_eq!(ids(&ordered), vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn load_order_skips_missing_and_circular_dependencies() {
+        let ordered = load_order(vec![
+            loaded("a", &["missing"]),
+            loaded("b", &["c"]),
+            loaded("c", &["b"]),
+            loaded("d", &[]),
+            loaded("e", &["a"])
+        ]);
+        assert_eq!(ids(&ordered), vec!["d"]);
+    }
+
+    #[test]
+    fn merge_defs_later_source_wins() {
+        let mut owners = HashMap::new();
+        let mut into = HashMap::from([("bone".to_string(), 1), ("eye".to_string(), 2)]);
+        merge_defs("ingredient", &mut into, HashMap::from([("bone".to_string(), 10)]), "first", &mut owners);
+        merge_defs("ingredient", &mut into, HashMap::from([("bone".to_string(), 20), ("web".to_string(), 3)]), "second", &mut owners);
+
+        assert_eq!(into["bone"], 20);
+        assert_eq!(into["eye"], 2);
+        assert_eq!(into["web"], 3);
+        assert_eq!(owners["ingredient bone"], "second");
+        assert!(!owners.contains_key("ingredient eye"));
+    }
+
+    #[test]
+    fn merge_recipes_replaces_by_id_and_appends_new() {
+        let mut owners = HashMap::new();
+        let mut into = vec![recipe("imp", "tooth_imp"), recipe("bat", "bat")];
+        merge_recipes(&mut into, vec![recipe("imp", "spider"), recipe("toad", "toad")], "spiders", &mut owners);
+
+        let merged: Vec<(&str, &str)> = into.iter().map(|r| (r.id.as_str(), r.demon.as_str())).collect();
+        assert_eq!(merged, vec![("bat", "bat"), ("imp", "spider"), ("toad", "toad")]);
+        assert_eq!(owners["recipe imp"], "spiders");
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use comfy::*;
+use crate::component::{Demon, Pickup};
+use crate::demon_ai::DemonConfig;
+
+/// Both fields are string table ids, e.g. `ingredient.skull.name`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TooltipDef {
+    pub name: String,
+    pub flavour: String
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecipeDef {
+    pub id: String,
+    pub ingredients: Vec<String>,
+    pub demon: String,
+    #[serde(default)]
+    pub sound: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DemonDef {
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// Movement and stealing tuning; missing fields take the defaults.
+    #[serde(default)]
+    pub behaviour: DemonConfig
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundBus {
+    Music,
+    Sfx
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SoundDef {
+    pub path: String,
+    pub bus: SoundBus
+}
+
+/// Sounds for interactions that are not tied to one recipe or demon.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GameSounds {
+    pub grab: Option<String>,
+    pub drop: Option<String>,
+    pub reject: Option<String>,
+    pub book_open: Option<String>,
+    pub book_close: Option<String>,
+    pub music: Option<String>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecipeData {
+    pub ingredients: HashMap<String, TooltipDef>,
+    pub book: TooltipDef,
+    pub summon_circle: TooltipDef,
+    #[serde(default)]
+    pub recipes: Vec<RecipeDef>,
+    #[serde(default)]
+    pub demons: HashMap<String, DemonDef>,
+    /// Optional. No sound files ship with the game yet, so the base data
+    /// names none; a mod can add them, e.g. with paths in its own folder.
+    #[serde(default)]
+    pub sounds: HashMap<String, SoundDef>,
+    #[serde(default)]
+    pub game_sounds: GameSounds
+}
+
+pub fn load_recipe_data_bytes(json_bytes: &[u8]) -> RecipeData {
+    return serde_json::from_slice(json_bytes).unwrap();
+}
+
+/// Recipe whose ingredient list matches the stack exactly, in order.
+pub fn find_recipe(stack: &Vec<Pickup>) -> Option<RecipeDef> {
+    for (_, data) in world().query::<&RecipeData>().iter() {
+        for r in data.recipes.iter() {
+            let is_match = r.ingredients.len() == stack.len()
+                && r.ingredients.iter().zip(stack.iter()).all(|(i, p)| i == p.id());
+            if is_match {
+                return Some(r.clone());
+            }
+        }
+    }
+
+    return None;
+}
+
+pub fn recipe_length() -> usize {
+    for (_, data) in world().query::<&RecipeData>().iter() {
+        return data.recipes.iter().map(|r| r.ingredients.len()).max().unwrap_or(3);
+    }
+
+    return 3;
+}
+
+pub fn demon_def(id: &str) -> Option<DemonDef> {
+    for (_, data) in world().query::<&RecipeData>().iter() {
+        return data.demons.get(id).cloned();
+    }
+
+    return None;
+}
+
+pub fn find_demon(id: &str) -> Option<Demon> {
+    return demon_def(id).map(|_| Demon { id: id.to_string() });
+}
+
+pub fn game_sounds() -> GameSounds {
+    for (_, data) in world().query::<&RecipeData>().iter() {
+        return data.game_sounds.clone();
+    }
+
+    return GameSounds::default();
+}
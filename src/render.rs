@@ -0,0 +1,195 @@
+use comfy::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use aseprite_loader::{ImageAtlas, TagHandle, draw_frame_tinted, find_layer, frame_in_layer};
+use crate::component::{self, Demon, Pickup, Sprite, Hidden};
+use crate::recipe_data::RecipeData;
+use crate::animation::Animator;
+use crate::atlas_names::tags;
+use log::warn;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum RenderLayer {
+    Background,
+    Book,
+    Held,
+    RecipeStack,
+    Effects,
+    Demons,
+    Overlay,
+    Letterbox
+}
+
+// Sub-orders are clamped to this so they never spill into the next layer.
+const LAYER_SPAN: i32 = 100;
+
+impl RenderLayer {
+    pub fn z(&self) -> i32 {
+        return match self {
+            RenderLayer::Background => 0,
+            RenderLayer::Book => 1,
+            RenderLayer::Held => 2,
+            RenderLayer::RecipeStack => 3,
+            RenderLayer::Effects => 4,
+            RenderLayer::Demons => 5,
+            RenderLayer::Overlay => 10,
+            RenderLayer::Letterbox => 20
+        } * LAYER_SPAN;
+    }
+
+    pub fn z_with(&self, sub_order: i32) -> i32 {
+        return self.z() + sub_order.clamp(0, LAYER_SPAN - 1);
+    }
+}
+
+pub struct RenderItem {
+    pub tag: TagHandle,
+    /// Atlas frame to draw instead of the tag's first frame, e.g. from an Animator.
+    pub frame: Option<usize>,
+    pub art_layer: Option<&'static str>,
+    pub pos: Vec2,
+    pub layer: RenderLayer,
+    pub sub_order: i32
+}
+
+pub struct RenderQueue {
+    pub items: Vec<RenderItem>
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self { items: vec![] }
+    }
+
+    pub fn push(&mut self, tag: TagHandle, pos: Vec2, layer: RenderLayer, sub_order: i32) {
+        self.items.push(RenderItem { tag, frame: None, art_layer: None, pos, layer, sub_order });
+    }
+}
+
+/// Ingredients and demons are data-defined, so their art is found by
+/// convention: `pickups/<id>` and `demons/<id>`.
+pub fn pickup_tag(p: &Pickup) -> String {
+    return format!("pickups/{}", p.id());
+}
+
+pub fn demon_tag(d: &Demon) -> String {
+    return format!("demons/{}", d.id());
+}
+
+/// Tag handles resolved once when the atlas loads, so spawning code can name
+/// tags without the atlas and nothing looks tags up by name while drawing.
+pub struct TagTable {
+    pub handles: HashMap<String, TagHandle>,
+    /// Keyed by ingredient id.
+    pub pickups: HashMap<String, TagHandle>,
+    pub book: Option<TagHandle>
+}
+
+pub fn new_tag_table(atlas: &ImageAtlas, recipes: &RecipeData) -> TagTable {
+    let mut pickups = HashMap::new();
+    for id in recipes.ingredients.keys() {
+        let tag = pickup_tag(&Pickup { id: id.clone() });
+        match atlas.tag_handle(&tag) {
+            None => warn!("No sprite tag {} for ingredient {}", tag, id),
+            Some(h) => { pickups.insert(id.clone(), h); }
+        }
+    }
+
+    return TagTable {
+        handles: atlas.tag_handles.clone(),
+        pickups,
+        book: atlas.tag_handle(tags::BOOK_BOOK)
+    };
+}
+
+pub fn tag_handle(name: &str) -> Option<TagHandle> {
+    for (_, table) in world().query::<&TagTable>().iter() {
+        return table.handles.get(name).copied();
+    }
+
+    return None;
+}
+
+pub fn pickup_tag_handle(p: &Pickup) -> Option<TagHandle> {
+    for (_, table) in world().query::<&TagTable>().iter() {
+        return table.pickups.get(p.id()).copied();
+    }
+
+    return None;
+}
+
+pub fn book_tag_handle() -> Option<TagHandle> {
+    for (_, table) in world().query::<&TagTable>().iter() {
+        return table.book;
+    }
+
+    return None;
+}
+
+/// Where an entity's sprite is centred when drawn.
+pub fn sprite_position(id: Entity) -> Vec2 {
+    let w = world();
+    if let Ok(m) = w.get::<&component::Motion>(id) {
+        return m.position;
+    }
+
+    return match w.get::<&component::Position>(id) {
+        Err(e) => vec2(0.0, 0.0),
+        Ok(p) => p.pos
+    };
+}
+
+pub fn queue_sprites(queue: &mut RenderQueue) {
+    for (id, (sprite, animator)) in world().query::<(&Sprite, Option<&Animator>)>().without::<&Hidden>().iter() {
+        queue.items.push(RenderItem {
+            tag: sprite.tag,
+            frame: animator.and_then(|a| a.current_frame()),
+            art_layer: sprite.art_layer,
+            pos: sprite_position(id),
+            layer: sprite.layer,
+            sub_order: sprite.sub_order
+        });
+    }
+}
+
+static MISSING_ART_LAYERS: Mutex<Vec<&'static str>> = Mutex::new(vec![]);
+
+/// Logs a missing art layer the first time it is asked for instead of every frame.
+fn warn_missing_art_layer(layer: &'static str) {
+    if let Ok(mut missing) = MISSING_ART_LAYERS.lock() {
+        if !missing.contains(&layer) {
+            warn!("Art layer {} is not in the atlas; export with --split-layers to draw it", layer);
+            missing.push(layer);
+        }
+    }
+}
+
+pub fn draw_render_queue(atlas: &ImageAtlas, queue: &mut RenderQueue) {
+    queue.items.sort_by_key(|item| (item.layer, item.sub_order));
+
+    for item in queue.items.iter() {
+        let maybe_frame = match item.frame {
+            Some(i) => atlas.frames.get(i),
+            None => atlas.first_frame(item.tag)
+        };
+        if maybe_frame.is_none() {
+            continue;
+        }
+
+        // Art layers missing from the export are skipped, so the game still
+        // runs on atlases exported without `--split-layers`.
+        let (frame, color) = match &item.art_layer {
+            None => (maybe_frame.unwrap(), WHITE),
+            Some(l) => match frame_in_layer(atlas, maybe_frame.unwrap(), l) {
+                None => {
+                    warn_missing_art_layer(l);
+                    continue;
+                },
+                Some(f) => (f, WHITE.alpha(find_layer(atlas, l).map_or(1.0, |x| x.opacity)))
+            }
+        };
+        draw_frame_tinted(atlas, frame, item.pos, color, item.layer.z_with(item.sub_order));
+    }
+
+    queue.items.clear();
+}
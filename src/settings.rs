@@ -0,0 +1,368 @@
+use std::env;
+use std::sync::OnceLock;
+use comfy::*;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::audio;
+use crate::input::{self, Action, Binding, PadState, ALL_ACTIONS};
+use crate::recipe_data::SoundBus;
+use crate::strings::{self, tr, tr_args};
+
+/// Path of the settings file; defaults to `settings.json` in the working directory.
+pub const SETTINGS_ENV: &str = "ASMODEUS_SETTINGS";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionMode {
+    Logical { width: u32, height: u32 },
+    Physical { width: u32, height: u32 }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HighlightPalette {
+    Default,
+    ColourBlind
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BindingSetting {
+    pub action: Action,
+    pub binding: String
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub resolution: ResolutionMode,
+    pub fullscreen: bool,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub bindings: Vec<BindingSetting>,
+    pub highlight_palette: HighlightPalette,
+    pub reduced_motion: bool,
+    pub language: String
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            resolution: ResolutionMode::Logical { width: 1280, height: 720 },
+            fullscreen: false,
+            music_volume: 0.6,
+            sfx_volume: 1.0,
+            bindings: vec![],
+            highlight_palette: HighlightPalette::Default,
+            reduced_motion: false,
+            language: strings::FALLBACK_LANGUAGE.to_string()
+        }
+    }
+}
+
+pub struct HighlightColors {
+    pub pressed: Color,
+    pub hovered: Color,
+    pub focused: Color
+}
+
+pub struct SettingsMenu {
+    pub visible: bool,
+    pub capturing: Option<Action>,
+    /// Changes applied while a slider is dragged, saved once it is let go.
+    pub is_unsaved: bool
+}
+
+pub fn new_settings_menu() -> SettingsMenu {
+    return SettingsMenu { visible: false, capturing: None, is_unsaved: false };
+}
+
+pub fn settings_path() -> String {
+    return env::var(SETTINGS_ENV).unwrap_or("settings.json".to_string());
+}
+
+pub fn load_settings() -> Settings {
+    let path = settings_path();
+    let bytes = match std::fs::read(&path) {
+        Err(_) => return Settings::default(),
+        Ok(b) => b
+    };
+
+    return match serde_json::from_slice(&bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Ignoring unreadable settings file {}: {}", path, e);
+            Settings::default()
+        }
+    };
+}
+
+static STARTUP_SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// The settings file as read at startup. `config` and `setup` both need it, so
+/// the file is read and parsed once.
+pub fn startup_settings() -> Settings {
+    return STARTUP_SETTINGS.get_or_init(load_settings).clone();
+}
+
+pub fn save_settings(settings: &Settings) {
+    let path = settings_path();
+    let result = serde_json::to_string_pretty(settings)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(()) => info!("Saved settings to {}", path),
+        Err(e) => warn!("Could not save settings to {}: {}", path, e)
+    }
+}
+
+pub fn resolution_config(mode: ResolutionMode) -> ResolutionConfig {
+    return match mode {
+        ResolutionMode::Logical { width, height } => ResolutionConfig::Logical(width, height),
+        ResolutionMode::Physical { width, height } => ResolutionConfig::Physical(width, height)
+    };
+}
+
+pub fn apply_to_config(settings: &Settings, config: GameConfig) -> GameConfig {
+    return GameConfig {
+        resolution: resolution_config(settings.resolution),
+        fullscreen: settings.fullscreen,
+        ..config
+    };
+}
+
+pub fn bindings_from_settings(settings: &Settings) -> Vec<(Action, Binding)> {
+    if settings.bindings.is_empty() {
+        return input::default_bindings();
+    }
+
+    let mut out = vec![];
+    for b in settings.bindings.iter() {
+        match input::binding_from_string(&b.binding) {
+            None => warn!("Unknown binding {} for {:?}", b.binding, b.action),
+            Some(binding) => out.push((b.action, binding))
+        }
+    }
+
+    return out;
+}
+
+fn bindings_to_settings(bindings: &Vec<(Action, Binding)>) -> Vec<BindingSetting> {
+    return bindings.iter()
+        .filter_map(|(action, b)| input::binding_to_string(b).map(|s| BindingSetting {
+            action: *action,
+            binding: s
+        }))
+        .collect_vec();
+}
+
+/// Pushes settings that take effect at runtime into the running systems.
+pub fn apply_runtime_settings(settings: &Settings) {
+    input::set_bindings(bindings_from_settings(settings));
+    audio::set_bus_volume(SoundBus::Music, settings.music_volume);
+    audio::set_bus_volume(SoundBus::Sfx, settings.sfx_volume);
+    strings::set_language(&settings.language);
+}
+
+pub fn current() -> Settings {
+    for (_, s) in world().query::<&Settings>().iter() {
+        return s.clone();
+    }
+
+    return Settings::default();
+}
+
+pub fn is_reduced_motion() -> bool {
+    for (_, s) in world().query::<&Settings>().iter() {
+        return s.reduced_motion;
+    }
+
+    return false;
+}
+
+pub fn highlight_colors() -> HighlightColors {
+    let mut palette = HighlightPalette::Default;
+    for (_, s) in world().query::<&Settings>().iter() {
+        palette = s.highlight_palette;
+    }
+
+    return match palette {
+        HighlightPalette::Default => HighlightColors {
+            pressed: Color::new(1.0, 0.0, 0.0, 0.25),
+            hovered: Color::new(0.0, 1.0, 0.0, 0.25),
+            focused: Color::new(1.0, 1.0, 0.0, 0.25)
+        },
+        // Okabe-Ito orange, blue and yellow stay distinct under the common
+        // forms of colour blindness.
+        HighlightPalette::ColourBlind => HighlightColors {
+            pressed: Color::new(0.90, 0.62, 0.0, 0.35),
+            hovered: Color::new(0.0, 0.45, 0.70, 0.35),
+            focused: Color::new(0.94, 0.89, 0.26, 0.35)
+        }
+    };
+}
+
+fn resolution_label(mode: &ResolutionMode) -> String {
+    return match mode {
+        ResolutionMode::Logical { width, height } => tr_args("settings.resolution_scaled", &[
+            ("width", width.to_string()), ("height", height.to_string())
+        ]),
+        ResolutionMode::Physical { width, height } => tr_args("settings.resolution_physical", &[
+            ("width", width.to_string()), ("height", height.to_string())
+        ])
+    };
+}
+
+const RESOLUTIONS: [ResolutionMode; 4] = [
+    ResolutionMode::Logical { width: 1280, height: 720 },
+    ResolutionMode::Logical { width: 1920, height: 1080 },
+    ResolutionMode::Physical { width: 1280, height: 720 },
+    ResolutionMode::Physical { width: 1920, height: 1080 }
+];
+
+/// Draws the settings window while it is open. Returns true when the player
+/// asked to quit the game from the menu.
+pub fn settings_spin(pad: &PadState) -> bool {
+    let mut visible = false;
+    let mut capturing = None;
+    let mut is_unsaved = false;
+    for (_, menu) in world_mut().query_mut::<&mut SettingsMenu>().into_iter() {
+        if input::is_action_pressed(Action::OpenSettings) && menu.capturing.is_none() {
+            menu.visible = !menu.visible;
+        }
+        visible = menu.visible;
+        capturing = menu.capturing;
+        is_unsaved = menu.is_unsaved;
+    }
+
+    if !visible {
+        if is_unsaved {
+            save_settings(&current());
+            for (_, menu) in world_mut().query_mut::<&mut SettingsMenu>().into_iter() {
+                menu.is_unsaved = false;
+            }
+        }
+        return false;
+    }
+
+    let mut settings = current();
+    let mut bindings = input::current_bindings();
+    let mut changed = false;
+    let mut quit = false;
+    let mut close = false;
+    let mut is_dragging = false;
+
+    if let Some(action) = capturing {
+        if let Some(b) = input::pressed_binding(pad) {
+            input::replace_binding(&mut bindings, action, b);
+            settings.bindings = bindings_to_settings(&bindings);
+            capturing = None;
+            changed = true;
+        }
+    }
+
+    let languages = strings::available_languages();
+    let language_label = languages.iter()
+        .find(|(code, _)| *code == settings.language)
+        .map(|(_, name)| name.clone())
+        .unwrap_or(settings.language.clone());
+
+    // The window id stays fixed so egui keeps its position across languages.
+    egui::Window::new(tr("settings.title")).id(egui::Id::new("settings")).collapsible(false).show(egui(), |ui| {
+        ui.heading(tr("settings.display"));
+        egui::ComboBox::from_label(tr("settings.resolution"))
+            .selected_text(resolution_label(&settings.resolution))
+            .show_ui(ui, |ui| {
+                for r in RESOLUTIONS.iter() {
+                    changed |= ui.selectable_value(&mut settings.resolution, *r, resolution_label(r)).changed();
+                }
+            });
+        changed |= ui.checkbox(&mut settings.fullscreen, tr("settings.fullscreen")).changed();
+        ui.label(tr("settings.restart_note"));
+        egui::ComboBox::from_label(tr("settings.language"))
+            .selected_text(language_label)
+            .show_ui(ui, |ui| {
+                for (code, name) in languages.iter() {
+                    changed |= ui.selectable_value(&mut settings.language, code.clone(), name).changed();
+                }
+            });
+
+        ui.separator();
+        ui.heading(tr("settings.audio"));
+        let music = ui.add(egui::Slider::new(&mut settings.music_volume, 0.0..=1.0).text(tr("settings.music")));
+        let sfx = ui.add(egui::Slider::new(&mut settings.sfx_volume, 0.0..=1.0).text(tr("settings.effects")));
+        changed |= music.changed() || sfx.changed();
+        // Restarting the track or rewriting the file on every step of a drag
+        // would stutter, so both wait until the slider is let go.
+        is_dragging = music.dragged() || sfx.dragged();
+
+        ui.separator();
+        ui.heading(tr("settings.accessibility"));
+        ui.horizontal(|ui| {
+            changed |= ui.radio_value(&mut settings.highlight_palette, HighlightPalette::Default, tr("settings.palette_default")).changed();
+            changed |= ui.radio_value(&mut settings.highlight_palette, HighlightPalette::ColourBlind, tr("settings.palette_colour_blind")).changed();
+        });
+        changed |= ui.checkbox(&mut settings.reduced_motion, tr("settings.reduced_motion")).changed();
+
+        ui.separator();
+        ui.heading(tr("settings.controls"));
+        for action in ALL_ACTIONS.iter() {
+            ui.horizontal(|ui| {
+                let names = bindings.iter()
+                    .filter(|(a, _)| a == action)
+                    .filter_map(|(_, b)| input::binding_to_string(b))
+                    .collect_vec();
+                ui.label(format!("{}: {}", tr(action.string_id()), names.join(", ")));
+                let label = if capturing == Some(*action) { tr("settings.press_key") } else { tr("settings.rebind") };
+                if ui.button(label).clicked() {
+                    capturing = Some(*action);
+                }
+            });
+        }
+        if ui.button(tr("settings.reset_controls")).clicked() {
+            bindings = input::default_bindings();
+            settings.bindings = vec![];
+            changed = true;
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            close = ui.button(tr("settings.close")).clicked();
+            quit = ui.button(tr("settings.quit")).clicked();
+        });
+    });
+
+    if changed {
+        apply_runtime_settings(&settings);
+        for (_, s) in world_mut().query_mut::<&mut Settings>().into_iter() {
+            *s = settings.clone();
+        }
+        is_unsaved = true;
+    }
+
+    if !is_dragging {
+        audio::apply_music_volume();
+        if is_unsaved {
+            save_settings(&settings);
+            is_unsaved = false;
+        }
+    }
+
+    for (_, menu) in world_mut().query_mut::<&mut SettingsMenu>().into_iter() {
+        menu.capturing = capturing;
+        menu.is_unsaved = is_unsaved;
+        if close {
+            menu.visible = false;
+        }
+    }
+
+    return quit;
+}
+
+pub fn is_menu_open() -> bool {
+    for (_, menu) in world().query::<&SettingsMenu>().iter() {
+        return menu.visible;
+    }
+
+    return false;
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use comfy::*;
+use log::warn;
+use crate::assets;
+
+/// Language used when a string is missing from the selected table.
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// Every shipped language, keyed by its code, with the asset path of its
+/// table. Each table carries its own display name under `language.name`.
+pub const LANGUAGES: [(&str, &str); 2] = [
+    ("en", "assets/data/strings/en.json"),
+    ("da", "assets/data/strings/da.json")
+];
+
+pub struct StringTable {
+    pub languages: HashMap<String, HashMap<String, String>>,
+    pub current: String
+}
+
+pub fn load_string_tables(language: &str) -> StringTable {
+    let mut languages = HashMap::new();
+    for (code, path) in LANGUAGES.iter() {
+        let bytes = match assets::try_read(path) {
+            Err(e) => {
+                warn!("Skipping missing string table {}: {}", code, e);
+                continue;
+            },
+            Ok(b) => b
+        };
+
+        match serde_json::from_slice(&bytes) {
+            Ok(strings) => { languages.insert(code.to_string(), strings); },
+            Err(e) => warn!("Skipping unreadable string table {}: {}", code, e)
+        }
+    }
+
+    let mut table = StringTable { languages, current: FALLBACK_LANGUAGE.to_string() };
+    select_language(&mut table, language);
+    return table;
+}
+
+fn select_language(table: &mut StringTable, language: &str) {
+    if table.languages.contains_key(language) {
+        table.current = language.to_string();
+    } else {
+        warn!("Unknown language {}, using {}", language, FALLBACK_LANGUAGE);
+        table.current = FALLBACK_LANGUAGE.to_string();
+    }
+}
+
+pub fn set_language(language: &str) {
+    for (_, table) in world_mut().query_mut::<&mut StringTable>().into_iter() {
+        select_language(table, language);
+    }
+}
+
+pub fn current_language() -> String {
+    for (_, table) in world().query::<&StringTable>().iter() {
+        return table.current.clone();
+    }
+
+    return FALLBACK_LANGUAGE.to_string();
+}
+
+/// Language codes paired with their display names, in shipping order.
+pub fn available_languages() -> Vec<(String, String)> {
+    let mut out = vec![];
+    for (_, table) in world().query::<&StringTable>().iter() {
+        for (code, _) in LANGUAGES.iter() {
+            let name = table.languages.get(*code)
+                .and_then(|s| s.get("language.name"))
+                .cloned()
+                .unwrap_or(code.to_string());
+            out.push((code.to_string(), name));
+        }
+    }
+
+    return out;
+}
+
+fn lookup(table: &StringTable, id: &str) -> Option<String> {
+    return table.languages.get(&table.current)
+        .and_then(|s| s.get(id))
+        .or(table.languages.get(FALLBACK_LANGUAGE).and_then(|s| s.get(id)))
+        .cloned();
+}
+
+/// Looks up a player-facing string in the selected language, then in the
+/// fallback language. Unknown ids come back verbatim so a missing entry
+/// shows up on screen instead of as a panic.
+pub fn tr(id: &str) -> String {
+    for (_, table) in world().query::<&StringTable>().iter() {
+        if let Some(s) = lookup(table, id) {
+            return s;
+        }
+    }
+
+    return id.to_string();
+}
+
+/// `tr` with `{name}` placeholders replaced by the given values.
+pub fn tr_args(id: &str, args: &[(&str, String)]) -> String {
+    let mut s = tr(id);
+    for (name, value) in args.iter() {
+        s = s.replace(&format!("{{{}}}", name), value);
+    }
+
+    return s;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> StringTable {
+        let en = HashMap::from([
+            ("menu.quit".to_string(), "Quit".to_string()),
+            ("menu.play".to_string(), "Play".to_string())
+        ]);
+        let da = HashMap::from([("menu.play".to_string(), "Spil".to_string())]);
+        let languages = HashMap::from([("en".to_string(), en), ("da".to_string(), da)]);
+        return StringTable { languages, current: "da".to_string() };
+    }
+
+    #[test]
+    fn prefers_the_selected_language() {
+        assert_eq!(lookup(&table(), "menu.play"), Some("Spil".to_string()));
+    }
+
+    #[test]
+    fn falls_back_to_english() {
+        assert_eq!(lookup(&table(), "menu.quit"), Some("Quit".to_string()));
+    }
+
+    #[test]
+    fn unknown_ids_are_not_found() {
+        assert_eq!(lookup(&table(), "menu.missing"), None);
+    }
+
+    #[test]
+    fn unknown_language_selects_english() {
+        let mut t = table();
+        select_language(&mut t, "xx");
+        assert_eq!(t.current, FALLBACK_LANGUAGE);
+    }
+}
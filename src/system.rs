@@ -1,8 +1,21 @@
 use std::ops::Add;
 
 use comfy::*;
-use crate::component::{self, IsHovered, Pickup, WasClicked, Lifetime, Demon, Motion, DrawNo, DrawWin};
-use crate::aseprite_loader::{ImageAtlas};
+use log::{debug, info, warn};
+use crate::component::{self, IsHovered, Pickup, Lifetime, Demon, Motion, DrawNo, DrawWin, Stolen};
+use aseprite_loader::{Frame, ImageAtlas, frame_alpha_at};
+use crate::demon_ai::new_demon_brain;
+use crate::render::{self, RenderLayer, demon_tag};
+use crate::camera;
+use crate::animation;
+use crate::atlas_names::tags;
+use crate::effects;
+use crate::input::{self, Action, PointerMode};
+use crate::events::{self, InteractionEvent};
+use crate::recipe_data::{self, RecipeDef};
+use crate::audio;
+use crate::settings;
+use crate::ui_text;
 
 pub fn get_world_click_box(model_box: &component::ClickBox, pos: &Vec2) -> component::ClickBox {
     let x = model_box.pos.x + pos.x;
@@ -25,84 +38,226 @@ pub fn world_clickbox_from_id(id: Entity) -> Option<component::ClickBox> {
     return Some(get_world_click_box(&maybe_model_click_box.unwrap(), &pos));
 }
 
+pub fn world_clickbox_center(id: Entity) -> Option<Vec2> {
+    let b = world_clickbox_from_id(id)?;
+    return Some(vec2(b.pos.x + b.size.x * 0.5, b.pos.y + b.size.y * 0.5));
+}
+
 fn is_point_inside_box(pos: &Vec2, size: &Vec2, point: &Vec2) -> bool {
     let is_outside = point.x < pos.x || point.y < pos.y || pos.x + size.x < point.x || pos.y + size.y < point.y;
     return !is_outside;
 }
 
-fn handle_mouse_pressed() {
-    if !is_mouse_button_pressed(MouseButton::Left) {
-        return;
+/// Frame and centre the entity is drawn with: its sprite's current frame at
+/// its sprite position, or for a level slice the frame in its `AlphaHit`.
+fn drawn_frame<'a>(atlas: &'a ImageAtlas, id: Entity) -> Option<(&'a Frame, Vec2)> {
+    let w = world();
+    if let Ok(sprite) = w.get::<&component::Sprite>(id) {
+        let animated = w.get::<&animation::Animator>(id).ok().and_then(|a| a.current_frame());
+        let frame = match animated {
+            Some(i) => atlas.frames.get(i),
+            None => atlas.first_frame(sprite.tag)
+        };
+        return frame.map(|f| (f, render::sprite_position(id)));
+    }
+
+    return match w.get::<&component::AlphaHit>(id) {
+        Err(e) => None,
+        Ok(hit) => atlas.frames.get(hit.frame).map(|f| (f, hit.origin))
+    };
+}
+
+/// Alpha of `frame` drawn centred on `origin`, at world position `point`.
+fn alpha_at_point(atlas: &ImageAtlas, frame: &Frame, origin: Vec2, point: &Vec2) -> u8 {
+    let local = ivec2(
+        (point.x - origin.x + (frame.source_size.w / 2) as f32).floor() as i32,
+        (origin.y - point.y + (frame.source_size.h / 2) as f32).floor() as i32
+    );
+    return frame_alpha_at(atlas, frame, local);
+}
+
+/// Clickable sprites are hit only on their visible pixels. Level slices opt
+/// in with `alpha_hit`, which only helps on level art with transparency such
+/// as a mod's overlay; the base background is opaque under every slice.
+fn passes_alpha_test(atlas: &ImageAtlas, id: Entity, point: &Vec2) -> bool {
+    let (frame, origin) = match drawn_frame(atlas, id) {
+        None => return true,
+        Some(f) => f
+    };
+    // Without a decoded mask every pixel would read as transparent.
+    if atlas.pages[frame.page].alpha_mask.is_none() {
+        return true;
     }
-    let m = mouse_world();
-    println!("Click {}", m);
 
+    return 0 < alpha_at_point(atlas, frame, origin, point);
+}
+
+fn click_order(id: Entity) -> (RenderLayer, i32) {
+    return match world().get::<&component::Sprite>(id) {
+        Err(e) => (RenderLayer::Background, 0),
+        Ok(s) => (s.layer, s.sub_order)
+    };
+}
+
+/// Topmost clickable entity under `point`. Higher render layers win, and
+/// within a layer the smaller box is treated as the more specific target.
+pub fn entity_under_point(atlas: &ImageAtlas, point: &Vec2) -> Option<Entity> {
+    let mut hits = vec![];
     let w = world();
-    for (id, clickbox) in world().query::<&component::ClickBox>().iter() {
+    for (id, clickbox) in w.query::<&component::ClickBox>().iter() {
         let p = match w.get::<&component::Position>(id) {
             Ok(v) => v.pos,
             Err(e) => vec2(0.0, 0.0)
         };
         let world_clickbox = get_world_click_box(clickbox, &p);
-        
-        if is_point_inside_box(&world_clickbox.pos, &world_clickbox.size, &m) {
-            let duration = 0.5;
-            commands().insert_one(id, component::WasClicked{duration: duration, time: duration});
+        if !is_point_inside_box(&world_clickbox.pos, &world_clickbox.size, point) {
+            continue;
+        }
+        if !passes_alpha_test(atlas, id, point) {
+            continue;
         }
+
+        let area = world_clickbox.size.x * world_clickbox.size.y;
+        hits.push((click_order(id), area, id));
     }
+
+    hits.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.total_cmp(&b.1)));
+    return hits.first().map(|h| h.2);
 }
 
-fn handle_was_clicked_cool_down() {
-    for (id, was_clicked) in world().query::<&mut component::WasClicked>().iter() {
-        was_clicked.time -= delta();
-        if was_clicked.time <= 0.0 || true {
-            commands().remove_one::<component::WasClicked>(id);
-        }
+/// Entity the player is pointing at. With keyboard or gamepad focus this is
+/// the focused entity itself, so alpha-masked boxes stay selectable.
+pub fn pointer_target(atlas: &ImageAtlas) -> Option<Entity> {
+    if input::pointer_mode() == PointerMode::Focus {
+        return input::focused_entity();
     }
+
+    return entity_under_point(atlas, &mouse_world());
 }
 
-fn handle_mouse_hover() {
+fn is_draggable(id: Entity) -> bool {
     let w = world();
-    let m = mouse_world();
-    for (id, clickbox) in world().query::<&component::ClickBox>().iter() {
-        let p = match w.get::<&component::Position>(id) {
-            Ok(v) => v.pos,
-            Err(e) => vec2(0.0, 0.0)
-        };
-        let world_clickbox = get_world_click_box(clickbox, &p);
+    return w.get::<&component::Pickup>(id).is_ok() && w.get::<&Stolen>(id).is_err();
+}
 
-        if is_point_inside_box(&world_clickbox.pos, &world_clickbox.size, &m) {
-            commands().insert_one(id, IsHovered{});
-        } else {
-            commands().remove_one::<IsHovered>(id);
+fn handle_select_pressed(atlas: &ImageAtlas) {
+    if !input::is_action_pressed(Action::Select) {
+        return;
+    }
+    let m = input::pointer_world();
+    debug!("Select at {}", m);
+
+    let maybe_target = pointer_target(atlas);
+
+    // Focus navigation has no drag gesture: a Select while dragging is the drop.
+    if input::pointer_mode() == PointerMode::Focus {
+        if let Some(source) = events::dragging() {
+            events::emit(InteractionEvent::Drop { source, target: maybe_target });
+            events::set_dragging(None);
+            return;
         }
     }
+
+    events::set_pressed_on(maybe_target);
+    let target = match maybe_target {
+        None => return,
+        Some(t) => t
+    };
+
+    events::emit(InteractionEvent::Pressed(target));
+    if is_draggable(target) {
+        events::emit(InteractionEvent::DragStart(target));
+        events::set_dragging(Some(target));
+    }
+
+    if input::pointer_mode() == PointerMode::Focus {
+        events::emit(InteractionEvent::Released(target));
+        events::emit(InteractionEvent::Clicked(target));
+        events::set_pressed_on(None);
+    }
+}
+
+fn handle_select_released(atlas: &ImageAtlas) {
+    if !input::is_action_released(Action::Select) || input::pointer_mode() == PointerMode::Focus {
+        return;
+    }
+
+    let maybe_target = pointer_target(atlas);
+    if let Some(target) = maybe_target {
+        events::emit(InteractionEvent::Released(target));
+        if events::pressed_on() == Some(target) {
+            events::emit(InteractionEvent::Clicked(target));
+        }
+    }
+
+    if let Some(source) = events::dragging() {
+        events::emit(InteractionEvent::Drop { source, target: maybe_target });
+    }
+
+    events::set_pressed_on(None);
+    events::set_dragging(None);
 }
 
-pub fn clickable_spin() {
-    handle_was_clicked_cool_down();
-    handle_mouse_pressed();
-    handle_mouse_hover();
+fn handle_cancel() {
+    if !input::is_action_pressed(Action::Cancel) {
+        return;
+    }
+
+    if let Some(source) = events::dragging() {
+        events::emit(InteractionEvent::Drop { source, target: None });
+    }
+    events::set_pressed_on(None);
+    events::set_dragging(None);
+}
+
+fn handle_mouse_hover(atlas: &ImageAtlas) {
+    let maybe_hovered = pointer_target(atlas);
+    let previous = events::hovered();
+    if maybe_hovered == previous {
+        return;
+    }
+
+    if let Some(p) = previous {
+        events::emit(InteractionEvent::HoverExit(p));
+        commands().remove_one::<IsHovered>(p);
+    }
+    if let Some(h) = maybe_hovered {
+        events::emit(InteractionEvent::HoverEnter(h));
+        commands().insert_one(h, IsHovered{});
+    }
+    events::set_hovered(maybe_hovered);
+}
+
+pub fn clickable_spin(atlas: &ImageAtlas) {
+    handle_mouse_hover(atlas);
+    handle_select_pressed(atlas);
+    handle_select_released(atlas);
+    handle_cancel();
 }
 
 pub fn is_clicked(id: Entity) -> bool {
-    return world().get::<&component::WasClicked>(id).is_ok();
-} 
+    return events::has_event(InteractionEvent::Clicked(id));
+}
 
 fn get_click_box_color(id: Entity) -> Color {
-    if world().get::<&component::WasClicked>(id).is_ok() {
-        return Color::new(1.0, 0.0, 0.0, 0.25);
+    let colors = settings::highlight_colors();
+    if events::pressed_on() == Some(id) {
+        return colors.pressed;
     }
 
     if world().get::<&component::IsHovered>(id).is_ok() {
-        return Color::new(0.0, 1.0, 0.0, 0.25);
+        return colors.hovered;
+    }
+
+    if world().get::<&component::Focused>(id).is_ok() {
+        return colors.focused;
     }
 
     return Color::new(1.0, 1.0, 1.0, 0.0)
 }
 
 pub fn clickable_draw() {
-    let t = 4.0;
+    let t = 1.0;
     let w = world();
     for (id, clickbox) in w.query::<&component::ClickBox>().iter() {
         let p = match w.get::<&component::Position>(id) {
@@ -111,15 +266,33 @@ pub fn clickable_draw() {
         };
         let world_box = get_world_click_box(clickbox, &p);
         let color = get_click_box_color(id);
+        let outline = w.get::<&component::SliceColor>(id).map_or(WHITE, |c| c.color.alpha(1.0));
         let o = vec2(world_box.size.x * 0.5, world_box.size.y * 0.5);
-        draw_rect(world_box.pos + o, world_box.size, color, 0);
-        draw_rect_outline(world_box.pos + o, world_box.size, t, WHITE, 0);
+        draw_rect(world_box.pos + o, world_box.size, color, RenderLayer::Overlay.z());
+        draw_rect_outline(world_box.pos + o, world_box.size, t, outline, RenderLayer::Overlay.z());
+    }
+}
+
+pub fn focus_draw() {
+    if input::pointer_mode() != PointerMode::Focus {
+        return;
+    }
+
+    let maybe_box = input::focused_entity().and_then(world_clickbox_from_id);
+    if let Some(b) = maybe_box {
+        let o = vec2(b.size.x * 0.5, b.size.y * 0.5);
+        let color = settings::highlight_colors().focused.alpha(1.0);
+        draw_rect_outline(b.pos + o, b.size, 1.0, color, RenderLayer::Overlay.z());
     }
 }
 
 pub fn was_something_picked_up() -> Option<component::Pickup> {
-    for (id, pickup) in world().query::<&component::Pickup>().iter() {
-        if (is_clicked(id)) {
+    for e in events::read() {
+        let id = match e {
+            InteractionEvent::DragStart(id) => id,
+            _ => continue
+        };
+        if let Ok(pickup) = world().get::<&component::Pickup>(id) {
             return Some(pickup.clone());
         }
     }
@@ -127,6 +300,10 @@ pub fn was_something_picked_up() -> Option<component::Pickup> {
     return None;
 }
 
+pub fn was_something_dropped() -> bool {
+    return events::read().iter().any(|e| matches!(e, InteractionEvent::Drop { .. }));
+}
+
 pub fn is_mouse_inside_summon_circle() -> bool {
     for (id, summon) in world().query::<&component::IsDropoff>().iter() {
         let maybe_world_clickbox = world_clickbox_from_id(id);
@@ -135,7 +312,7 @@ pub fn is_mouse_inside_summon_circle() -> bool {
         }
 
         let world_clickbox = maybe_world_clickbox.unwrap();
-        let m = mouse_world();
+        let m = input::pointer_world();
         if is_point_inside_box(&world_clickbox.pos, &world_clickbox.size, &m) {
             return true;
         }
@@ -152,9 +329,9 @@ pub fn pickup_spin() {
         }
     }
 
-    if is_mouse_button_released(MouseButton::Left) {
+    if was_something_dropped() {
         let maybe_pickup = get_pickup_from_state();
-        let should_append = is_mouse_inside_summon_circle();
+        let should_append = is_mouse_inside_summon_circle() && !input::is_action_pressed(Action::Cancel);
 
         for (_, gs) in world_mut().query_mut::<&mut component::GlobalGameState>().into_iter() {
             gs.pickup = None;
@@ -162,6 +339,10 @@ pub fn pickup_spin() {
                 gs.recipe_stack.push(maybe_pickup.clone().unwrap());
             }
         }
+
+        if maybe_pickup.is_some() && should_append {
+            effects::spawn_effect("ingredient_drop", input::pointer_world());
+        }
     }
 }
 
@@ -184,7 +365,16 @@ pub fn was_recipe_book_clicked() -> bool {
 }
 
 pub fn recipe_book_spin() {
-    if is_mouse_button_pressed(MouseButton::Left) {
+    if input::is_action_pressed(Action::OpenBook) {
+        for (_, gs) in world_mut().query_mut::<&mut component::GlobalGameState>().into_iter() {
+            gs.show_recipe_book = !gs.show_recipe_book;
+        }
+        return;
+    }
+
+    // Any press closes the book, including one on empty table space that
+    // hits no clickbox and so emits no `Pressed` event.
+    if input::is_action_pressed(Action::Select) || input::is_action_pressed(Action::Cancel) {
         for (_, gs) in world_mut().query_mut::<&mut component::GlobalGameState>().into_iter() {
             gs.show_recipe_book = false;
         }    
@@ -212,73 +402,161 @@ pub fn get_recipe_stack() -> Vec<component::Pickup> {
     return vec![];
 }
 
+// Optional art: not every atlas has it, so it is not in `atlas_names`.
+const SUMMON_ANIMATION_TAG: &str = "summon/summon";
+const SPAWN_DEMON_EVENT: &str = "spawn_demon";
+
+fn spawn_demon(demon: Demon) {
+    let tag_name = demon_tag(&demon);
+    let tag = match render::tag_handle(&tag_name) {
+        None => {
+            warn!("No sprite tag {} for demon {}", tag_name, demon.id());
+            return;
+        },
+        Some(t) => t
+    };
+
+    let home = ui_text::summon_circle_center().unwrap_or(Vec2::ZERO);
+    let rx = random() * 2.0 - 1.0;
+    let ry = random();
+
+    commands().spawn(
+        (
+            component::Sprite {
+                tag,
+                art_layer: None,
+                layer: RenderLayer::Demons,
+                sub_order: 0
+            },
+            demon,
+            new_demon_brain(home),
+            Motion {
+                position: home,
+                velocity: vec2(rx * 200.0, ry * 200.0),
+                gravity: vec2(0.0, -300.0)
+            },
+        )
+    );
+}
+
+/// The demon a summon releases on animation event `name`: at the
+/// `spawn_demon` frame, or when the animation ends without one. At most once.
+fn release_demon(name: &str, pending: &mut component::PendingSummon) -> Option<Demon> {
+    let is_release = name == SPAWN_DEMON_EVENT || name == animation::FINISHED;
+    if !is_release || pending.has_spawned {
+        return None;
+    }
+
+    pending.has_spawned = true;
+    return Some(pending.demon.clone());
+}
+
+pub fn pending_summon_spin() {
+    for e in animation::read() {
+        let mut to_spawn = None;
+        if let Ok(mut pending) = world().get::<&mut component::PendingSummon>(e.source) {
+            to_spawn = release_demon(&e.name, &mut pending);
+        }
+
+        if let Some(demon) = to_spawn {
+            spawn_demon(demon);
+        }
+        if e.name == animation::FINISHED {
+            commands().despawn(e.source);
+        }
+    }
+}
+
+/// Plays a successful summon without the win screen, so the round goes on.
+pub fn summon_demon(recipe: &RecipeDef) -> bool {
+    info!("Summon succeeded: {}", recipe.id);
+    let demon = match recipe_data::find_demon(&recipe.demon) {
+        None => {
+            warn!("Recipe {} names unknown demon {}", recipe.id, recipe.demon);
+            return false;
+        },
+        Some(d) => d
+    };
+
+    audio::play_named(&recipe.sound);
+    audio::play_named(&recipe_data::demon_def(&recipe.demon).and_then(|d| d.sound));
+    if let Some(center) = ui_text::summon_circle_center() {
+        effects::spawn_effect("summon_success", center);
+    }
+
+    camera::shake(3.0, 0.4);
+
+    // The demon appears on the animation's spawn_demon frame, or when it
+    // ends if it has none. Atlases without the tag finish at once.
+    let summon_tag = render::tag_handle(SUMMON_ANIMATION_TAG);
+    let animator = animation::new_animator(summon_tag, false);
+    let pending = component::PendingSummon { demon, has_spawned: false };
+    match summon_tag {
+        None => commands().spawn((animator, pending)),
+        Some(tag) => commands().spawn((
+            animator,
+            pending,
+            component::Sprite { tag, art_layer: None, layer: RenderLayer::Effects, sub_order: 0 }
+        ))
+    }
+
+    return true;
+}
+
+/// A summon from the recipe stack: the demon, then the win screen, which
+/// ends the game when it expires.
+pub fn summon_success(recipe: &RecipeDef) {
+    if !summon_demon(recipe) {
+        return;
+    }
+
+    let lifetime = Lifetime {time: 0.0, duration: 2.0};
+    match render::tag_handle(tags::NO_WIN) {
+        None => commands().spawn((DrawWin {}, lifetime)),
+        Some(tag) => commands().spawn((
+            DrawWin {},
+            lifetime,
+            component::Sprite { tag, art_layer: None, layer: RenderLayer::Overlay, sub_order: 0 }
+        ))
+    }
+    ui_text::spawn_message("summon.accepted", 2.0);
+}
+
+pub fn summon_failure() {
+    info!("Summon rejected");
+    audio::play_named(&recipe_data::game_sounds().reject);
+    if let Some(center) = ui_text::summon_circle_center() {
+        effects::spawn_effect("summon_failure", center);
+    }
+    let lifetime = Lifetime {time: 0.0, duration: 2.0};
+    match render::tag_handle(tags::NO_NO) {
+        None => commands().spawn((DrawNo {}, lifetime)),
+        Some(tag) => commands().spawn((
+            DrawNo {},
+            lifetime,
+            component::Sprite { tag, art_layer: None, layer: RenderLayer::Overlay, sub_order: 0 }
+        ))
+    }
+    ui_text::spawn_message("summon.rejected", 2.0);
+}
+
 pub fn recipe_stack_spin() {
-    let correct_answers: Vec<component::Pickup> = [
-        component::Pickup::Knife {  },
-        component::Pickup::Eye {  },
-        component::Pickup::Fire {  }
-    ].to_vec();
-     
     let current_stack = get_recipe_stack();
 
-    if 3 <= current_stack.len() {
+    if recipe_data::recipe_length() <= current_stack.len() {
         for (_, gs) in world_mut().query_mut::<&mut component::GlobalGameState>().into_iter() {
             gs.recipe_stack.clear();
         }
 
-        if current_stack == correct_answers {
-            commands().spawn((
-                ParticleSystem::with_spawn_on_death(300, || {
-                    Particle {
-                        texture: texture_id("atlas"),
-                        position: random_circle(5.0),
-                        size: splat(10.0),
-                        size_curve: expo_out,
-                        z_index: 30,
-                        angular_velocity: random() * 10.0,
-                        // Both size and color can be faded.
-                        fade_type: FadeType::Both,
-                        color_start: RED,
-                        color_end: RED,
-                        ..Default::default()
-                    }
-                }),
-                Transform::position(vec2(10.0, 0.0)),
-                Lifetime {time: 0.0, duration: 2.0}
-            ));
-
-            let rx = random() * 2.0 - 1.0;
-            let ry = random();
-
-            commands().spawn(
-                (
-                    Demon::ToothImp {},
-                    Lifetime {time: 0.0, duration: 2.0},
-                    Motion {
-                        position: vec2(10.0, 0.0),
-                        velocity: vec2(rx * 200.0, ry * 200.0),
-                        gravity: vec2(0.0, -300.0)
-                    },
-                )
-            );
-            commands().spawn(
-                (
-                    DrawWin {},
-                    Lifetime {time: 0.0, duration: 2.0}
-                )
-            );
-        } else {
-            commands().spawn(
-                (
-                    DrawNo {},
-                    Lifetime {time: 0.0, duration: 2.0}
-                )
-            );
+        match recipe_data::find_recipe(&current_stack) {
+            Some(recipe) => summon_success(&recipe),
+            None => summon_failure()
         }
     }
 }
 
-pub fn Lifetime_spin() {
+/// Returns true once the win screen has run its course.
+pub fn Lifetime_spin() -> bool {
     for (id, lifetime) in world_mut().query::<&mut component::Lifetime>().iter() {
         lifetime.time += delta();
 
@@ -290,10 +568,12 @@ pub fn Lifetime_spin() {
     for (id, lifetime) in world().query::<&component::Lifetime>().iter() {
         if lifetime.duration <= lifetime.time {
             if world().get::<&component::DrawWin>(id).is_ok() {
-                panic!();
+                return true;
             }
         }
     }
+
+    return false;
 }
 
 pub fn motion_spin() {
@@ -302,4 +582,70 @@ pub fn motion_spin() {
         motion.velocity += motion.gravity * dt;
         motion.position += motion.velocity * dt
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aseprite_loader::{AlphaMask, parse_atlas_json};
+
+    const SUMMON_ATLAS: &[u8] = include_bytes!("../aseprite_loader/tests/data/summon_events.json");
+
+    /// Plays the fixture's summon animation in 50ms steps, returning the step
+    /// and event name of every release.
+    fn releases(atlas: &ImageAtlas) -> Vec<(usize, String)> {
+        let id = Entity::from_bits((1 << 32) | 1).unwrap();
+        let mut animator = animation::new_animator(atlas.tag_handle(SUMMON_ANIMATION_TAG), false);
+        let mut pending = component::PendingSummon { demon: Demon { id: "tooth_imp".to_string() }, has_spawned: false };
+
+        let mut out = vec![];
+        for step in 0..20 {
+            let mut fired = vec![];
+            animation::advance(id, atlas, &mut animator, 0.05, &mut fired);
+            for e in fired.iter() {
+                if let Some(demon) = release_demon(&e.name, &mut pending) {
+                    assert_eq!(demon.id(), "tooth_imp");
+                    out.push((step, e.name.clone()));
+                }
+            }
+        }
+
+        return out;
+    }
+
+    #[test]
+    fn alpha_follows_the_frame_pixels_around_its_centre() {
+        // Frame 0 of the fixture is a 10x10 sprite at the atlas origin; only
+        // its left half is opaque.
+        let mut alpha = vec![0; 40 * 10];
+        for y in 0..10 {
+            for x in 0..5 {
+                alpha[y * 40 + x] = 255;
+            }
+        }
+        let mask = AlphaMask { width: 40, height: 10, alpha };
+        let atlas = ImageAtlas::from_data(parse_atlas_json(SUMMON_ATLAS).unwrap(), texture_id("summon"), Some(mask));
+        let frame = &atlas.frames[0];
+        let origin = vec2(100.0, 50.0);
+
+        assert_eq!(alpha_at_point(&atlas, frame, origin, &vec2(98.0, 50.0)), 255);
+        assert_eq!(alpha_at_point(&atlas, frame, origin, &vec2(102.0, 50.0)), 0);
+        assert_eq!(alpha_at_point(&atlas, frame, origin, &vec2(95.5, 54.5)), 255);
+        // Outside the frame altogether.
+        assert_eq!(alpha_at_point(&atlas, frame, origin, &vec2(94.0, 50.0)), 0);
+    }
+
+    #[test]
+    fn summon_releases_the_demon_at_the_spawn_frame() {
+        let atlas = ImageAtlas::from_data(parse_atlas_json(SUMMON_ATLAS).unwrap(), texture_id("summon"), None);
+        // Frames last 100ms, so the third frame starts on the fourth 50ms step.
+        assert_eq!(releases(&atlas), vec![(3, SPAWN_DEMON_EVENT.to_string())]);
+    }
+
+    #[test]
+    fn summon_without_the_tag_releases_the_demon_at_once() {
+        let mut data = parse_atlas_json(SUMMON_ATLAS).unwrap();
+        data.tag_handles.clear();
+        let atlas = ImageAtlas::from_data(data, texture_id("summon"), None);
+        assert_eq!(releases(&atlas), vec![(0, animation::FINISHED.to_string())]);
+    }
+}
@@ -0,0 +1,64 @@
+use comfy::*;
+use crate::component::{self, Tooltip};
+use crate::recipe_data::{RecipeData, TooltipDef};
+use crate::events;
+use crate::input;
+use crate::strings::tr;
+
+fn default_tooltip<'a>(w: &World, id: Entity, recipes: &'a RecipeData) -> Option<&'a TooltipDef> {
+    if let Ok(p) = w.get::<&component::Pickup>(id) {
+        return recipes.ingredients.get(p.id());
+    }
+    if w.get::<&component::IsBook>(id).is_ok() {
+        return Some(&recipes.book);
+    }
+    if w.get::<&component::IsSummonCircle>(id).is_ok() {
+        return Some(&recipes.summon_circle);
+    }
+
+    return None;
+}
+
+/// Tooltip for a level slice. The slice's own `name` and `flavour` string ids
+/// win over the recipe data file, so artists can label one-off props.
+pub fn tooltip_for_slice(
+        w: &World, id: Entity, value: &serde_json::Value, recipes: &RecipeData
+) -> Option<Tooltip> {
+    let fallback = default_tooltip(w, id, recipes);
+    let title = value["name"].as_str()
+        .map(|s| s.to_string())
+        .or(fallback.map(|t| t.name.clone()));
+    let text = value["flavour"].as_str()
+        .map(|s| s.to_string())
+        .or(fallback.map(|t| t.flavour.clone()));
+
+    if title.is_none() && text.is_none() {
+        return None;
+    }
+
+    return Some(Tooltip {
+        title_id: title.unwrap_or_default(),
+        text_id: text.unwrap_or_default()
+    });
+}
+
+pub fn tooltip_draw() {
+    if events::dragging().is_some() {
+        return;
+    }
+
+    let hovered = match events::hovered() {
+        None => return,
+        Some(h) => h
+    };
+
+    let w = world();
+    let tooltip = match w.get::<&Tooltip>(hovered) {
+        Err(e) => return,
+        Ok(t) => t
+    };
+
+    let pos = input::pointer_world() + vec2(6.0, -6.0);
+    draw_text(&tr(&tooltip.title_id), pos, WHITE, TextAlign::TopLeft);
+    draw_text(&tr(&tooltip.text_id), pos + vec2(0.0, -8.0), Color::new(0.8, 0.8, 0.8, 1.0), TextAlign::TopLeft);
+}
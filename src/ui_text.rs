@@ -0,0 +1,64 @@
+use comfy::*;
+use crate::component::{self, Lifetime, Message};
+use crate::recipe_data;
+use crate::strings::{tr, tr_args};
+use crate::system::{get_recipe_stack, world_clickbox_center};
+
+const SLOT_SPACING: f32 = 22.0;
+const SLOT_HEIGHT: f32 = 26.0;
+
+pub fn summon_circle_center() -> Option<Vec2> {
+    for (id, _) in world().query::<&component::IsSummonCircle>().iter() {
+        if let Some(center) = world_clickbox_center(id) {
+            return Some(center);
+        }
+    }
+
+    return None;
+}
+
+/// Where the `index`th ingredient of a `total` long recipe sits: a row of
+/// slots centred above the summon circle.
+pub fn slot_position(center: Vec2, index: usize, total: usize) -> Vec2 {
+    let x = (index as f32 - (total as f32 - 1.0) * 0.5) * SLOT_SPACING;
+    return center + vec2(x, SLOT_HEIGHT);
+}
+
+pub fn slot_labels_draw() {
+    let center = match summon_circle_center() {
+        None => return,
+        Some(c) => c
+    };
+
+    let total = recipe_data::recipe_length();
+    let filled = get_recipe_stack().len();
+    for i in 0..total {
+        let label = tr_args("summon.slot", &[
+            ("index", (i + 1).to_string()),
+            ("total", total.to_string())
+        ]);
+        let color = match i < filled {
+            true => WHITE,
+            false => Color::new(1.0, 1.0, 1.0, 0.4)
+        };
+        let pos = slot_position(center, i, total) + vec2(0.0, -12.0);
+        draw_text(&label, pos, color, TextAlign::Center);
+    }
+}
+
+pub fn spawn_message(text_id: &str, duration: f32) {
+    commands().spawn((
+        Message { text_id: text_id.to_string() },
+        Lifetime { time: 0.0, duration }
+    ));
+}
+
+pub fn messages_draw() {
+    let mut row = 0.0;
+    for (_, (message, lifetime)) in world().query::<(&Message, &Lifetime)>().iter() {
+        let fade = 1.0 - (lifetime.time / lifetime.duration).clamp(0.0, 1.0);
+        let color = Color::new(1.0, 0.9, 0.8, fade);
+        draw_text(&tr(&message.text_id), vec2(0.0, 70.0 - row), color, TextAlign::Center);
+        row += 10.0;
+    }
+}